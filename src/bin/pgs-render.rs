@@ -0,0 +1,168 @@
+//! Renders a saved configuration to a PNG without opening a window.
+
+use std::process::ExitCode;
+use std::time::Instant;
+
+use pentagon_gardening_simulator::{
+    config::{Configuration, MoveLog},
+    puzzle::PuzzleDefinition,
+    render,
+};
+
+const USAGE: &str = "\
+Usage: pgs-render <CONFIG> <OUTPUT> [OPTIONS]
+
+Autofills the puzzle in CONFIG to completion and writes the image to OUTPUT.
+CONFIG may also be a move log, in which case the scrambled state is rendered
+pixel by pixel.
+
+Options:
+  --size <W>x<H>     Image size in pixels [default: 2000x2000]
+  --scale <SCALE>    Scale of the view, as in the app [default: 0.5]
+  --depth <DEPTH>    Orbit search depth [default: from CONFIG]
+  --camera <CONFIG>  Use the camera saved in another configuration
+  --distance <D>     Move the circles onto a ring with distance D
+  --radius <R>       Set the radius of every circle to R
+  --texture <PNG>    Render pixel by pixel with a picture in place of the
+                     solved colours, scrambled by any moves in CONFIG";
+
+struct Args {
+    config: String,
+    output: String,
+    size: [u32; 2],
+    scale: f64,
+    depth: Option<u32>,
+    camera: Option<String>,
+    distance: Option<f64>,
+    radius: Option<f64>,
+    texture: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut positional = vec![];
+    let mut args = Args {
+        config: String::new(),
+        output: String::new(),
+        size: [2000, 2000],
+        scale: 0.5,
+        depth: None,
+        camera: None,
+        distance: None,
+        radius: None,
+        texture: None,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--help" {
+            return Err(String::new());
+        }
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"))?;
+        let invalid = || format!("Invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--size" => {
+                let (w, h) = value.split_once('x').ok_or_else(invalid)?;
+                args.size = [
+                    w.parse().map_err(|_| invalid())?,
+                    h.parse().map_err(|_| invalid())?,
+                ];
+            }
+            "--scale" => args.scale = value.parse().map_err(|_| invalid())?,
+            "--depth" => args.depth = Some(value.parse().map_err(|_| invalid())?),
+            "--camera" => args.camera = Some(value),
+            "--distance" => args.distance = Some(value.parse().map_err(|_| invalid())?),
+            "--radius" => args.radius = Some(value.parse().map_err(|_| invalid())?),
+            "--texture" => args.texture = Some(value),
+            _ => return Err(format!("Unknown option {arg}")),
+        }
+    }
+    let [config, output] = <[String; 2]>::try_from(positional)
+        .map_err(|_| "Expected a configuration and an output path".to_string())?;
+    args.config = config;
+    args.output = output;
+    Ok(args)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}\n");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let load = |path: &str| Configuration::load(path).map_err(|e| format!("{path}: {e}"));
+    let (mut config, moves) = match MoveLog::load(&args.config) {
+        Ok(log) => (log.config, Some(log.moves)),
+        Err(e) if e.is_not_move_log() => (load(&args.config)?, None),
+        Err(e) => return Err(format!("{}: {e}", args.config)),
+    };
+    if let Some(path) = &args.camera {
+        config.camera = load(path)?.camera;
+    }
+    if let Some(depth) = args.depth {
+        config.depth = depth;
+    }
+    if let Some(distance) = args.distance {
+        config.circle_distance = distance;
+        let puzzle = PuzzleDefinition::new(config.circles, config.curvature);
+        config.circles = puzzle.with_ring_distance(distance).circles;
+    }
+    if let Some(radius) = args.radius {
+        for circle in &mut config.circles {
+            circle.circle.rad = radius;
+        }
+    }
+    config
+        .validate()
+        .map_err(|e| format!("{}: {e}", args.config))?;
+
+    let texture = match &args.texture {
+        Some(path) => Some(render::Image::load_png(path).map_err(|e| format!("{path}: {e}"))?),
+        None => None,
+    };
+
+    let view = render::View {
+        width: args.size[0],
+        height: args.size[1],
+        scale: args.scale,
+        camera: config.camera.clone(),
+    };
+    let start = Instant::now();
+    let image = match (moves, &texture) {
+        (None, None) => render::render(&config.explorer(), &view),
+        (moves, texture) => render::render_state(
+            &config.explorer(),
+            &moves.unwrap_or_default(),
+            &view,
+            texture.as_ref(),
+        ),
+    };
+    image
+        .save_png(&args.output)
+        .map_err(|e| format!("{}: {e}", args.output))?;
+    println!(
+        "Rendered {} in {:.1}s",
+        args.output,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
@@ -0,0 +1,317 @@
+//! Saving and loading puzzle configurations and move logs as RON.
+
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::geom::{Curvature, MobiusTransform, Pos, RotCircle};
+use crate::puzzle::{Explorer, Move, PuzzleDefinition};
+
+/// Everything needed to restore a puzzle and the view of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Configuration {
+    /// Format version, see [`Configuration::VERSION`]
+    pub version: u32,
+    pub circles: Vec<RotCircle>,
+    pub curvature: Curvature,
+    /// Ring distance the circles were generated with
+    pub circle_distance: f64,
+    pub depth: u32,
+    pub camera: MobiusTransform,
+}
+impl Configuration {
+    /// Newest format version this build can read and the one it writes.
+    /// Before version 2, circles in flat space turned clockwise.
+    pub const VERSION: u32 = 2;
+
+    pub fn new(explorer: &Explorer, circle_distance: f64, camera: &MobiusTransform) -> Self {
+        Self {
+            version: Self::VERSION,
+            circles: explorer.puzzle.circles.clone(),
+            curvature: explorer.puzzle.curvature,
+            circle_distance,
+            depth: explorer.depth,
+            camera: camera.clone(),
+        }
+    }
+
+    pub fn explorer(&self) -> Explorer {
+        Explorer::new(
+            PuzzleDefinition::new(self.circles.clone(), self.curvature),
+            self.depth,
+        )
+    }
+
+    pub fn to_ron(&self) -> Result<String, ConfigError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(ConfigError::Serialize)
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self, ConfigError> {
+        let config: Self = ron::from_str(s).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the configuration can be read by this build and describes
+    /// a puzzle that can be explored.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.version > Self::VERSION {
+            return Err(ConfigError::UnsupportedVersion(self.version));
+        }
+        if self.circles.is_empty() {
+            return Err(ConfigError::NoCircles);
+        }
+        for (index, circle) in self.circles.iter().enumerate() {
+            for component in circle.components() {
+                let invalid = |reason| Err(ConfigError::InvalidCircle { index, reason });
+                let circle = &component.circle;
+                if !circle.cen.is_in_space(self.curvature) {
+                    return invalid("centre is outside the space");
+                }
+                if !(circle.rad.is_finite() && circle.rad > 0.) {
+                    return invalid("radius is not positive");
+                }
+                let hole = component.hole_rad;
+                if !(hole.is_finite() && hole >= 0. && hole <= RotCircle::MAX_HOLE * circle.rad) {
+                    return invalid("hole is too big for the circle");
+                }
+                let ideal_len = circle.kind.ideal().map(|ideal| ideal.dist(&Pos::ORIGIN));
+                if ideal_len.is_some_and(|len| !(len.is_finite() && len > 0.)) {
+                    return invalid("ideal point has no direction");
+                }
+                if component.step == 0 && component.custom_angle.is_none() {
+                    return invalid("step is zero");
+                }
+                if !component.angle().is_finite() {
+                    return invalid("angle is not finite");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        std::fs::write(path, self.to_ron()?).map_err(ConfigError::Io)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_ron(&std::fs::read_to_string(path).map_err(ConfigError::Io)?)
+    }
+}
+
+/// A configuration with a sequence of twists from its solved state, such as
+/// a scramble followed by a solution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveLog {
+    pub config: Configuration,
+    pub moves: Vec<Move>,
+}
+impl MoveLog {
+    pub fn to_ron(&self) -> Result<String, ConfigError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(ConfigError::Serialize)
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self, ConfigError> {
+        let mut log: Self = ron::from_str(s).map_err(ConfigError::Parse)?;
+        log.config.validate()?;
+        log.upgrade();
+        Ok(log)
+    }
+
+    /// Converts a log from an older version to the current one, so that it
+    /// replays the same.
+    fn upgrade(&mut self) {
+        if self.config.version < 2 && self.config.curvature == Curvature::EUCLIDEAN {
+            for mv in &mut self.moves {
+                *mv = mv.reversed();
+            }
+        }
+        self.config.version = Configuration::VERSION;
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        std::fs::write(path, self.to_ron()?).map_err(ConfigError::Io)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_ron(&std::fs::read_to_string(path).map_err(ConfigError::Io)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+    NoCircles,
+    /// Circle `index`, or one linked to it, can't be explored
+    InvalidCircle {
+        index: usize,
+        reason: &'static str,
+    },
+}
+impl ConfigError {
+    /// Whether a move log failed to load only because the file has no
+    /// configuration in it, as when it is a plain configuration instead.
+    pub fn is_not_move_log(&self) -> bool {
+        matches!(
+            self,
+            ConfigError::Parse(e)
+                if matches!(e.code, ron::Error::MissingStructField { field: "config", .. })
+        )
+    }
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{e}"),
+            ConfigError::Parse(e) => write!(f, "Invalid configuration: {e}"),
+            ConfigError::Serialize(e) => write!(f, "Could not serialize configuration: {e}"),
+            ConfigError::UnsupportedVersion(v) => write!(
+                f,
+                "Configuration version {v} is newer than the supported version {}",
+                Configuration::VERSION
+            ),
+            ConfigError::NoCircles => write!(f, "Configuration has no circles"),
+            ConfigError::InvalidCircle { index, reason } => {
+                write!(f, "Invalid circle {index}: {reason}")
+            }
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::geom::{CircleKind, Curvature, MobiusTransform, Pos, RotCircle};
+    use crate::puzzle::Move;
+
+    use super::{ConfigError, Configuration, MoveLog};
+
+    fn config(circles: Vec<RotCircle>) -> Configuration {
+        Configuration {
+            version: Configuration::VERSION,
+            circles,
+            curvature: Curvature::EUCLIDEAN,
+            circle_distance: 1.,
+            depth: 100,
+            camera: MobiusTransform::IDENT,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let circle = RotCircle::new(Pos::new(0.5, 0.), 0.5, 5, Curvature::EUCLIDEAN, false);
+        let ron = config(vec![circle]).to_ron().unwrap();
+        let loaded = Configuration::from_ron(&ron).unwrap();
+        assert_eq!(loaded.circles.len(), 1);
+    }
+
+    #[test]
+    fn rejects_empty_circles() {
+        let ron = config(vec![]).to_ron().unwrap();
+        assert!(matches!(
+            Configuration::from_ron(&ron),
+            Err(ConfigError::NoCircles)
+        ));
+    }
+
+    #[test]
+    fn rejects_zero_radius() {
+        let circle = RotCircle::new(Pos::ORIGIN, 0., 5, Curvature::EUCLIDEAN, false);
+        let ron = config(vec![circle]).to_ron().unwrap();
+        assert!(matches!(
+            Configuration::from_ron(&ron),
+            Err(ConfigError::InvalidCircle { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_hole_filling_circle() {
+        let mut circle = RotCircle::new(Pos::ORIGIN, 0.5, 5, Curvature::EUCLIDEAN, false);
+        circle.hole_rad = 0.5;
+        let ron = config(vec![circle]).to_ron().unwrap();
+        assert!(matches!(
+            Configuration::from_ron(&ron),
+            Err(ConfigError::InvalidCircle { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_hole_beyond_largest() {
+        let mut circle = RotCircle::new(Pos::ORIGIN, 0.5, 5, Curvature::EUCLIDEAN, false);
+        circle.hole_rad = 0.97 * 0.5;
+        let ron = config(vec![circle.clone()]).to_ron().unwrap();
+        assert!(matches!(
+            Configuration::from_ron(&ron),
+            Err(ConfigError::InvalidCircle { index: 0, .. })
+        ));
+        circle.hole_rad = RotCircle::MAX_HOLE * 0.5;
+        assert!(config(vec![circle]).validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_ideal_without_direction() {
+        for ideal in [
+            Pos::ORIGIN,
+            Pos::new(f64::NAN, 0.),
+            Pos::new(f64::INFINITY, 1.),
+        ] {
+            let mut circle = RotCircle::new(Pos::ORIGIN, 0.5, 5, Curvature::HYPERBOLIC, false);
+            circle.circle.kind = CircleKind::Hypercycle { ideal };
+            let config = Configuration {
+                curvature: Curvature::HYPERBOLIC,
+                ..config(vec![circle])
+            };
+            assert!(matches!(
+                config.validate(),
+                Err(ConfigError::InvalidCircle { index: 0, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn tells_configurations_from_bad_move_logs() {
+        let circle = RotCircle::new(Pos::new(0.5, 0.), 0.5, 5, Curvature::EUCLIDEAN, false);
+        let ron = config(vec![circle.clone()]).to_ron().unwrap();
+        assert!(MoveLog::from_ron(&ron).unwrap_err().is_not_move_log());
+
+        let log = MoveLog {
+            config: config(vec![circle]),
+            moves: vec!["0".parse().unwrap()],
+        };
+        let bad_move = log.to_ron().unwrap().replace("\"0\"", "\"x\"");
+        assert!(!MoveLog::from_ron(&bad_move).unwrap_err().is_not_move_log());
+        let no_circles = MoveLog {
+            config: config(vec![]),
+            ..log
+        };
+        let error = MoveLog::from_ron(&no_circles.to_ron().unwrap()).unwrap_err();
+        assert!(!error.is_not_move_log());
+    }
+
+    #[test]
+    fn upgrade_reverses_flat_moves() {
+        let circle = RotCircle::new(Pos::new(0.5, 0.), 0.5, 5, Curvature::EUCLIDEAN, false);
+        let moves = vec!["0".parse::<Move>().unwrap(), "0'".parse().unwrap()];
+        let log = |version, curvature| MoveLog {
+            config: Configuration {
+                version,
+                curvature,
+                ..config(vec![circle.clone()])
+            },
+            moves: moves.clone(),
+        };
+        let load = |log: MoveLog| MoveLog::from_ron(&log.to_ron().unwrap()).unwrap();
+
+        let old_flat = load(log(1, Curvature::EUCLIDEAN));
+        assert_eq!(old_flat.config.version, Configuration::VERSION);
+        let reversed = moves.iter().map(|mv| mv.reversed()).collect::<Vec<_>>();
+        assert_eq!(old_flat.moves, reversed);
+        assert_eq!(load(log(1, Curvature::SPHERICAL)).moves, moves);
+        assert_eq!(load(log(2, Curvature::EUCLIDEAN)).moves, moves);
+    }
+}
@@ -1,27 +1,94 @@
 use hypermath::collections::approx_hashmap::{ApproxHashMapKey, FloatHash};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
-use crate::gfx::CircleInstance;
-
-pub(crate) struct GraphicsCircle {
+/// A filled circle in screen space, ready to be stamped onto the canvas.
+pub struct GraphicsCircle {
     pub centre: [f32; 2],
     pub radius: f32,
     pub col: [f32; 4],
+    /// Whether to fill everything outside the circle instead, for a stamp
+    /// reaching past infinity
+    pub inverted: bool,
 }
 impl GraphicsCircle {
-    pub fn get_instance(&self, scale: [f32; 2]) -> CircleInstance {
-        CircleInstance {
-            col: self.col,
-            centre: [self.centre[0] * scale[0], self.centre[1] * scale[1]],
-            scale: [scale[0] * self.radius, scale[1] * self.radius],
+    /// Fills the inside of `shape`, with a half-plane as a very large disc.
+    pub fn from_shape(shape: Shape, col: [f32; 4]) -> Self {
+        let (cen, rad, inverted) = match shape {
+            Shape::Disc { cen, rad } => (cen, rad, false),
+            Shape::Exterior { cen, rad } => (cen, rad, true),
+            Shape::HalfPlane { .. } => {
+                let (cen, rad) = shape.fill_disc().expect("Half-planes fill a disc");
+                (cen, rad, false)
+            }
+        };
+        Self {
+            centre: cen.into(),
+            radius: rad as f32,
+            col,
+            inverted,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub(crate) struct Circle {
+/// What a circle is centred on. In the hyperbolic plane the centre can move
+/// off to an ideal point, giving a horocycle, or spread out along a geodesic,
+/// giving a hypercycle.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub enum CircleKind {
+    /// The points less than `rad` from `cen`
+    #[default]
+    Point,
+    /// The horoball at the ideal point in the direction `ideal`, a unit
+    /// vector, whose boundary is `rad` from `cen`, on the side away from the
+    /// ideal point
+    Horocycle { ideal: Pos },
+    /// The points less than `rad` to the right of the geodesic running
+    /// through `cen` to the ideal point in the direction `ideal`
+    Hypercycle { ideal: Pos },
+}
+impl CircleKind {
+    /// The direction of the ideal point, if there is one.
+    pub fn ideal(self) -> Option<Pos> {
+        match self {
+            CircleKind::Point => None,
+            CircleKind::Horocycle { ideal } | CircleKind::Hypercycle { ideal } => Some(ideal),
+        }
+    }
+
+    /// The same kind with its ideal point at `ideal` instead.
+    pub fn with_ideal(self, ideal: Pos) -> Self {
+        match self {
+            CircleKind::Point => CircleKind::Point,
+            CircleKind::Horocycle { .. } => CircleKind::Horocycle { ideal },
+            CircleKind::Hypercycle { .. } => CircleKind::Hypercycle { ideal },
+        }
+    }
+
+    /// The same kind with the ideal point moved by `f`, an isometry of the
+    /// disk of radius `bound`, which keeps orientation. Outside hyperbolic
+    /// space there is no ideal point to move, so the direction is kept.
+    fn moved_by(self, f: impl Fn(Pos) -> Pos, bound: f64) -> Self {
+        match self.ideal() {
+            // The direction is a unit vector whether or not rounding error
+            // has moved the point off the boundary
+            Some(ideal) if bound.is_finite() => {
+                let moved = f(bound * ideal);
+                self.with_ideal((1. / moved.dist(&Pos::ORIGIN)) * moved)
+            }
+            _ => self,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Circle {
     pub cen: Pos,
     pub rad: f64,
     pub curvature: Curvature,
+    /// Only used in hyperbolic space, and treated as a point elsewhere
+    #[serde(default)]
+    pub kind: CircleKind,
 }
 impl Circle {
     pub fn new(cen: Pos, rad: f64, curvature: Curvature) -> Self {
@@ -29,160 +96,425 @@ impl Circle {
             cen,
             rad,
             curvature,
+            kind: CircleKind::Point,
         }
     }
 
     pub fn contains(&self, point: &Pos) -> bool {
-        self.cen.dist_in_space(point, self.curvature) < self.rad
-    }
-
-    pub fn euclidean_centre_radius(&self, transform: &MobiusTransform) -> (Pos, f64) {
-        let transformed_cen = transform.apply_to(self.cen);
-        match self.curvature {
-            Curvature::Spherical => {
-                // let t = (self.rad.tan().powi(2) + 1.).sqrt();
-                // let ap = (t - 1.) / self.rad.tan();
-                // let qp =
-                //     (ap + self.cen.dist(&Pos::ORIGIN)) / (1. - ap * self.cen.dist(&Pos::ORIGIN));
-                // let am = (-t - 1.) / self.rad.tan();
-                // let qm =
-                // (am + self.cen.dist(&Pos::ORIGIN)) / (1. - am * self.cen.dist(&Pos::ORIGIN));
-
-                let p_mod = transformed_cen.dist(&Pos::ORIGIN);
-                let p_mod_sq = transformed_cen.dist_sq(&Pos::ORIGIN);
-
-                let (s, c) = self.rad.sin_cos();
-                let qp = (2. * c * p_mod - (p_mod_sq - 1.) * s)
-                    / (1. + c + p_mod_sq * (1. - c) - 2. * p_mod * s);
-                let qm = (2. * c * p_mod + (p_mod_sq - 1.) * s)
-                    / (1. + c + p_mod_sq * (1. - c) + 2. * p_mod * s);
-
-                let cen = if transformed_cen.dist(&Pos::ORIGIN) == 0. {
-                    Pos::ORIGIN
-                } else {
-                    (qp + qm) / (2. * transformed_cen.dist(&Pos::ORIGIN)) * transformed_cen
-                };
-                let rad = (qp - qm) / 2.;
-                (cen, rad.abs())
-            }
-            Curvature::Euclidean => (transformed_cen, self.rad),
-            Curvature::Hyperbolic => {
-                let p_mod = transformed_cen.dist(&Pos::ORIGIN);
-                let p_mod_sq = transformed_cen.dist_sq(&Pos::ORIGIN);
-
-                let (s, c) = (self.rad.sinh(), self.rad.cosh());
-                let qp = -(-2. * c * p_mod + p_mod_sq * s + s)
-                    / (c * p_mod_sq + c - p_mod_sq - 2. * p_mod * s + 1.);
-                let qm = -(-2. * c * p_mod - p_mod_sq * s - s)
-                    / (c * p_mod_sq + c - p_mod_sq + 2. * p_mod * s + 1.);
-
-                let cen = if transformed_cen.dist(&Pos::ORIGIN) == 0. {
-                    Pos::ORIGIN
-                } else {
-                    (qp + qm) / (2. * transformed_cen.dist(&Pos::ORIGIN)) * transformed_cen
-                };
-                let rad = (qp - qm) / 2.;
-                (cen, rad.abs())
+        self.level(point) < self.rad
+    }
+
+    /// Distance from `point` to the edge of the circle.
+    pub fn dist_to_boundary(&self, point: &Pos) -> f64 {
+        (self.level(point) - self.rad).abs()
+    }
+
+    /// The distance of `point` from the centre, or for horocycles and
+    /// hypercycles its signed distance from the horocycle through `cen` or
+    /// from the geodesic. The circle is where this is less than `rad`.
+    fn level(&self, point: &Pos) -> f64 {
+        let Some(ideal) = self.ideal() else {
+            return self.cen.dist_in_space(point, self.curvature);
+        };
+        // The chart measures distances at curvature -1
+        let w = self.half_plane_chart(ideal).apply_to(*point);
+        let level = match self.kind {
+            CircleKind::Horocycle { .. } => -w.y.ln(),
+            _ => (w.x / w.y).asinh(),
+        };
+        level / self.unit_scale()
+    }
+
+    /// The direction of the ideal point of a horocycle or hypercycle in
+    /// hyperbolic space, or `None` for a circle about a point.
+    fn ideal(&self) -> Option<Pos> {
+        self.kind.ideal().filter(|_| self.curvature.is_hyperbolic())
+    }
+
+    /// Factor by which distances grow when the space is scaled to curvature
+    /// -1, as the half-plane chart does.
+    fn unit_scale(&self) -> f64 {
+        self.curvature.k().abs().sqrt()
+    }
+
+    /// A chart of the hyperbolic plane as the upper half-plane with curvature
+    /// -1, taking `cen` to `i` and the ideal point in the direction `ideal`
+    /// to infinity. Horocycles about the ideal point become horizontal lines,
+    /// and the geodesic through `cen` the imaginary axis.
+    pub fn half_plane_chart(&self, ideal: Pos) -> MobiusTransform {
+        let one = Pos::new(1., 0.);
+        let i = Pos::new(0., 1.);
+        let to_origin = MobiusTransform::to_origin(self.cen, self.curvature);
+        let end = to_origin.apply_to(self.curvature.model_bound() * ideal);
+        // Turns and shrinks the disk so that the ideal point lands on 1
+        let turn = MobiusTransform::new([
+            [
+                (1. / end.dist_sq(&Pos::ORIGIN)) * end.conjugate(),
+                Pos::ORIGIN,
+            ],
+            [Pos::ORIGIN, one],
+        ]);
+        // Takes the unit disc to the half-plane, 1 to infinity and 0 to i
+        let cayley = MobiusTransform::new([[i, i], [-one, one]]);
+        cayley * turn * to_origin
+    }
+
+    /// The direction of the other ideal end of the geodesic through `cen`
+    /// to the ideal point in the direction `ideal`.
+    fn opposite_ideal(&self, ideal: Pos) -> Pos {
+        let to_origin = MobiusTransform::to_origin(self.cen, self.curvature);
+        let end = to_origin.apply_to(self.curvature.model_bound() * ideal);
+        let opposite = to_origin.inverse().apply_to(-end);
+        (1. / opposite.dist(&Pos::ORIGIN)) * opposite
+    }
+
+    /// The circle as a generalised circle in the model of its space.
+    pub fn generalised(&self) -> GeneralisedCircle {
+        let chart_form = |ideal, form| {
+            self.half_plane_chart(ideal)
+                .inverse()
+                .apply_to_circle(&form)
+        };
+        let rad = self.rad * self.unit_scale();
+        match (self.kind, self.ideal()) {
+            // In the half-plane chart these are the half-planes `Im w > exp(-rad)`
+            // and `Re w < sinh(rad) Im w`
+            (CircleKind::Horocycle { .. }, Some(ideal)) => chart_form(
+                ideal,
+                GeneralisedCircle::new(0., Pos::new(0., -0.5), (-rad).exp()),
+            ),
+            (CircleKind::Hypercycle { .. }, Some(ideal)) => chart_form(
+                ideal,
+                GeneralisedCircle::new(0., Pos::new(0.5, -0.5 * rad.sinh()), 0.),
+            ),
+            _ => {
+                // The disc about the origin, moved out to the centre
+                MobiusTransform::to_origin(self.cen, self.curvature)
+                    .inverse()
+                    .apply_to_circle(&self.curvature.origin_disc(self.rad))
             }
         }
     }
+
+    /// What the circle looks like through `transform`.
+    pub fn shape(&self, transform: &MobiusTransform) -> Shape {
+        transform.apply_to_circle(&self.generalised()).shape()
+    }
 }
 
 use crate::Pos;
 
-use super::{Curvature, MobiusTransform};
+use super::{Curvature, GeneralisedCircle, MobiusTransform, Shape};
+
+/// Part of a move of a generator, from [`RotCircle::turn`].
 #[derive(Debug, Clone)]
-pub(crate) struct RotCircle {
+pub struct Turn<'a> {
+    circle: &'a RotCircle,
+    /// The transformation of each component
+    transforms: Vec<MobiusTransform>,
+}
+impl Turn<'_> {
+    /// Where `point` ends up, or `None` if the generator doesn't turn it.
+    /// This agrees with [`RotCircle::turn_point`].
+    pub fn apply(&self, point: Pos) -> Option<Pos> {
+        let component = self.circle.component_index(&point)?;
+        Some(self.transforms[component].apply_to(point))
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotCircle {
     pub circle: Circle,
+    /// Denominator of the fraction of a full turn made by one move
     pub step: u32,
+    /// Numerator of the fraction of a full turn made by one move
+    #[serde(default = "one")]
+    pub turns: u32,
+    /// Angle in radians to turn by instead of `turns / step` of a full turn,
+    /// such as an irrational multiple of one
+    #[serde(default)]
+    pub custom_angle: Option<f64>,
+    /// Radius of a concentric hole which doesn't turn, making the generator
+    /// a ring, or 0 for a disc
+    #[serde(default)]
+    pub hole_rad: f64,
+    /// Angle from the real axis, at the centre, of a geodesic to reflect the
+    /// inside of the circle across instead of turning it. Ignored for
+    /// horocycles and hypercycles.
+    #[serde(default)]
+    pub mirror: Option<f64>,
     pub inverted: bool,
+    /// Other circles turned by the same move, each by its own angle, as
+    /// when discs are geared or bandaged together. Their own links are
+    /// ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked: Vec<RotCircle>,
+}
+
+fn one() -> u32 {
+    1
 }
 impl RotCircle {
+    /// Largest radius of a hole as a fraction of the radius of the circle,
+    /// so that a ring always has something to turn.
+    pub const MAX_HOLE: f64 = 0.95;
+
     pub fn new(cen: Pos, rad: f64, step: u32, curvature: Curvature, inverted: bool) -> Self {
         let circle = Circle::new(cen, rad, curvature);
         Self {
             circle,
             step,
+            turns: 1,
+            custom_angle: None,
+            hole_rad: 0.,
+            mirror: None,
             inverted,
+            linked: vec![],
         }
     }
 
-    pub fn rotate_point(&self, point: Pos) -> Pos {
-        let theta = std::f64::consts::TAU / self.step as f64;
-        match self.circle.curvature {
-            Curvature::Spherical => {
-                // φ = exp(iθ/2)
-                // p = centre
+    /// Angle turned by one move, not counting any linked circles. Horocycles
+    /// slide this far along their boundary, and hypercycles along their
+    /// geodesic.
+    pub fn angle(&self) -> f64 {
+        self.custom_angle
+            .unwrap_or(std::f64::consts::TAU * self.turns as f64 / self.step as f64)
+    }
 
-                // M = [ φ+φ¯|p|^2   p¯(-φ+φ¯) ]
-                //     [ p(-φ+φ¯)    φ¯+φ|p|^2 ]
+    /// This circle followed by those linked to it.
+    pub fn components(&self) -> impl Iterator<Item = &RotCircle> {
+        std::iter::once(self).chain(&self.linked)
+    }
 
-                // M * (z,1) = ( φz+φ¯z|p|^2 + p¯(-φ+φ¯) , p(-φ+φ¯)z + φ¯+φ|p|^2 )
-                //           = ( φz+φ¯z|p|^2 + p¯(-φ+φ¯) , p(-φ+φ¯)z + φ¯+φ|p|^2 )
+    /// The first component turning `point`.
+    pub fn component_at(&self, point: &Pos) -> Option<&RotCircle> {
+        self.components().find(|c| c.contains_own(point))
+    }
 
-                // = (az-b¯ , bz+a¯)
+    /// Index in [`Self::components`] of the first component turning `point`.
+    pub fn component_index(&self, point: &Pos) -> Option<usize> {
+        self.components().position(|c| c.contains_own(point))
+    }
 
-                // out = az-b¯ / bz+a¯
+    /// Whether the regions turned by some component of each might meet.
+    /// Half-planes count as the large discs they are drawn as, so this can
+    /// report overlaps which aren't there but never misses one.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.components().any(|a| {
+            other.components().any(|b| {
+                a.own_region().iter().any(|p| {
+                    b.own_region().iter().any(|q| {
+                        !p.iter()
+                            .chain(q)
+                            .tuple_combinations()
+                            .any(|(s, t)| s.disjoint(*t))
+                    })
+                })
+            })
+        })
+    }
 
-                let phi = Pos::new((theta / 2.).cos(), (theta / 2.).sin());
-                let a = phi + self.circle.cen.dist_sq(&Pos::ORIGIN) * phi.conjugate();
-                let b = Pos::new(0., -2. * phi.y) * self.circle.cen.conjugate();
+    /// The region turned by this component alone, as a union of
+    /// intersections of shapes in the model.
+    fn own_region(&self) -> Vec<Vec<Shape>> {
+        let outer = self.circle.generalised();
+        let hole = self.hole().map(|hole| hole.generalised());
+        match self.inverted {
+            false => vec![std::iter::once(outer)
+                .chain(hole.map(|hole| hole.complement()))
+                .map(|c| c.shape())
+                .collect()],
+            true => std::iter::once(outer.complement())
+                .chain(hole)
+                .map(|c| vec![c.shape()])
+                .collect(),
+        }
+    }
 
-                let az = a * point;
-                let bz = b * point;
+    /// Where `point` ends up after one move.
+    pub fn rotate_point(&self, point: Pos) -> Pos {
+        self.turn_point(point, 1.)
+    }
 
-                let num = az - b.conjugate();
-                let den = bz + a.conjugate();
+    /// Where `point` ends up after `fraction` of a move, negative to turn the
+    /// other way. Only the first component containing `point` moves it, so
+    /// turning by `-fraction` takes it back.
+    pub fn turn_point(&self, point: Pos, fraction: f64) -> Pos {
+        match self.component_at(&point) {
+            Some(c) => c.move_point(point, fraction),
+            None => point,
+        }
+    }
 
-                num / den
-            }
-            Curvature::Euclidean => {
-                let x = point.x - self.circle.cen.x;
-                let y = point.y - self.circle.cen.y;
-                let x2 = theta.cos() * x + theta.sin() * y;
-                let y2 = theta.cos() * y - theta.sin() * x;
-                let x = x2 + self.circle.cen.x;
-                let y = y2 + self.circle.cen.y;
-
-                Pos { x, y }
-            }
-            Curvature::Hyperbolic => {
-                let phi = Pos::new((theta / 2.).cos(), (theta / 2.).sin());
-                let a = phi - self.circle.cen.dist_sq(&Pos::ORIGIN) * phi.conjugate();
-                let b = Pos::new(0., 2. * phi.y) * self.circle.cen.conjugate();
+    /// Moves `point` by `fraction` of this circle's own move, whether or not
+    /// it is inside the circle.
+    fn move_point(&self, point: Pos, fraction: f64) -> Pos {
+        self.transform(fraction).apply_to(point)
+    }
 
-                let az = a * point;
-                let bz = b * point;
+    /// `fraction` of a move with each component's transformation worked out
+    /// once, for turning many points.
+    pub fn turn(&self, fraction: f64) -> Turn<'_> {
+        Turn {
+            circle: self,
+            transforms: self.components().map(|c| c.transform(fraction)).collect(),
+        }
+    }
 
-                let num = az + b.conjugate();
-                let den = bz + a.conjugate();
+    /// `fraction` of this circle's own move, not counting any linked
+    /// circles, as a transformation of the whole plane. A reflection can't be
+    /// made gradually, so it happens halfway through.
+    pub fn transform(&self, fraction: f64) -> MobiusTransform {
+        if let Some(ideal) = self.circle.ideal() {
+            return self.slide(ideal, self.angle() * fraction);
+        }
+        match self.mirror {
+            Some(angle) if fraction.abs() >= 0.5 => self.reflection(angle),
+            Some(_) => MobiusTransform::IDENT,
+            None => self.rotation(self.angle() * fraction),
+        }
+    }
 
-                num / den
+    /// Moves by `distance` along the horocycles about `ideal`, measured along
+    /// the boundary, or along the geodesic through the centre to `ideal` for
+    /// a hypercycle.
+    fn slide(&self, ideal: Pos, distance: f64) -> MobiusTransform {
+        let one = Pos::new(1., 0.);
+        let chart = self.circle.half_plane_chart(ideal);
+        // In the half-plane a parabolic move is a horizontal translation, and
+        // a hyperbolic move a dilation about 0
+        let scale = self.circle.unit_scale();
+        let (distance, rad) = (distance * scale, self.circle.rad * scale);
+        let inner = match self.circle.kind {
+            CircleKind::Horocycle { .. } => {
+                let shift = distance * (-rad).exp();
+                MobiusTransform::new([[one, Pos::new(shift, 0.)], [Pos::ORIGIN, one]])
             }
+            _ => MobiusTransform::new([[distance.exp() * one, Pos::ORIGIN], [Pos::ORIGIN, one]]),
+        };
+        chart.inverse() * inner * chart
+    }
+
+    /// Reflection across the geodesic through the centre at `angle`, by
+    /// moving the centre to the origin and reflecting across a line there.
+    fn reflection(&self, angle: f64) -> MobiusTransform {
+        let to_origin = MobiusTransform::to_origin(self.circle.cen, self.circle.curvature);
+        to_origin.inverse() * MobiusTransform::reflection(angle) * to_origin
+    }
+
+    /// Rotation about the centre by `theta` anticlockwise.
+    fn rotation(&self, theta: f64) -> MobiusTransform {
+        MobiusTransform::rotation_about(self.circle.cen, theta, self.circle.curvature)
+    }
+
+    /// This circle moved by the isometry `transform`. The centre is moved,
+    /// and the mirror turned by the derivative at the centre, which gives
+    /// the new angle in every space since the models are conformal.
+    ///
+    /// A reflection swaps the sides of a hypercycle's geodesic, so the
+    /// geodesic is turned round to keep the moved points on its right.
+    fn moved_by(&self, transform: &MobiusTransform) -> Self {
+        let cen = self.circle.cen;
+        let mut ret = self.clone();
+        ret.circle.cen = transform.apply_to(cen);
+        let bound = self.circle.curvature.model_bound();
+        ret.circle.kind = self
+            .circle
+            .kind
+            .moved_by(|point| transform.apply_to(point), bound);
+        if let (true, CircleKind::Hypercycle { .. }, Some(ideal)) =
+            (transform.is_anti(), self.circle.kind, ret.circle.ideal())
+        {
+            ret.circle.kind = CircleKind::Hypercycle {
+                ideal: ret.circle.opposite_ideal(ideal),
+            };
         }
+        ret.mirror = self.mirror.map(|angle| {
+            let along = transform.apply_to_tangent(cen, Pos::new(angle.cos(), angle.sin()));
+            along.y.atan2(along.x)
+        });
+        ret
+    }
+
+    /// Moves `circle` and its linked circles by this circle's own move,
+    /// ignoring links, or by the opposite move if `inverse`.
+    pub fn rotate_circle(&self, circle: &Self, inverse: bool) -> Self {
+        let fraction = match inverse {
+            true => -1.,
+            false => 1.,
+        };
+        let transform = self.transform(fraction);
+        let mut ret = circle.moved_by(&transform);
+        ret.linked = circle
+            .linked
+            .iter()
+            .map(|c| c.moved_by(&transform))
+            .collect();
+        ret
     }
 
-    pub fn rotate_circle(&self, circle: &Self) -> Self {
+    /// This circle and its linked circles moved together along the geodesic
+    /// taking the centre to `cen`.
+    pub fn moved_to(&self, cen: Pos) -> Self {
+        let curvature = self.circle.curvature;
+        let transform = MobiusTransform::to_origin(cen, curvature).inverse()
+            * MobiusTransform::to_origin(self.circle.cen, curvature);
+        let mut ret = self.moved_by(&transform);
+        ret.linked = self.linked.iter().map(|c| c.moved_by(&transform)).collect();
+        ret
+    }
+
+    /// The same circle centred on `cen`, as where it has been moved by other
+    /// generators, without any linked circles.
+    pub fn with_centre(&self, cen: Pos) -> Self {
         Self {
             circle: Circle {
-                cen: self.rotate_point(circle.circle.cen),
-                ..circle.circle.clone()
+                cen,
+                ..self.circle.clone()
             },
-            ..circle.clone()
+            linked: vec![],
+            ..self.clone()
         }
     }
 
+    /// The inner boundary of a ring, or `None` for a disc. A hole too big
+    /// for the circle is shrunk to [`Self::MAX_HOLE`] of it.
+    pub fn hole(&self) -> Option<Circle> {
+        (self.hole_rad > 0.).then(|| Circle {
+            rad: self.hole_rad.min(Self::MAX_HOLE * self.circle.rad),
+            ..self.circle.clone()
+        })
+    }
+
+    /// The circle and its hole if there is one, followed by those of each
+    /// linked circle.
+    pub fn boundaries(&self) -> impl Iterator<Item = Circle> + '_ {
+        self.components()
+            .flat_map(|c| std::iter::once(c.circle.clone()).chain(c.hole()))
+    }
+
+    /// Whether a move turns `point`.
     pub fn contains(&self, point: &Pos) -> bool {
-        self.circle.contains(point) ^ self.inverted
+        self.component_at(point).is_some()
+    }
+
+    fn contains_own(&self, point: &Pos) -> bool {
+        let in_hole = self.hole().is_some_and(|hole| hole.contains(point));
+        (self.circle.contains(point) && !in_hole) ^ self.inverted
     }
 
-    pub fn euclidean_centre_radius(&self, transform: &MobiusTransform) -> (Pos, f64) {
-        self.circle.euclidean_centre_radius(transform)
+    pub fn shape(&self, transform: &MobiusTransform) -> Shape {
+        self.circle.shape(transform)
     }
 }
 
 impl ApproxHashMapKey for RotCircle {
-    type Hash = (<Pos as ApproxHashMapKey>::Hash, FloatHash, u32);
+    type Hash = (
+        <Pos as ApproxHashMapKey>::Hash,
+        FloatHash,
+        FloatHash,
+        Option<FloatHash>,
+        Option<<Pos as ApproxHashMapKey>::Hash>,
+    );
 
     fn approx_hash(
         &self,
@@ -193,7 +525,59 @@ impl ApproxHashMapKey for RotCircle {
         (
             self.circle.cen.approx_hash(&mut float_hash_fn),
             float_hash_fn(self.circle.rad),
-            self.step,
+            float_hash_fn(self.angle()),
+            self.mirror
+                .map(|angle| float_hash_fn(angle.rem_euclid(std::f64::consts::PI))),
+            self.circle
+                .kind
+                .ideal()
+                .map(|ideal| ideal.approx_hash(&mut float_hash_fn)),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::geom::{Curvature, Pos};
+
+    use super::{CircleKind, RotCircle};
+
+    #[test]
+    fn reflected_hypercycle_keeps_its_points() {
+        let curvature = Curvature::HYPERBOLIC;
+        let ideal = Pos::new(0.6, 0.8);
+        let mirror = |cen, angle| {
+            let mut mirror = RotCircle::new(cen, 0.5, 2, curvature, false);
+            mirror.mirror = Some(angle);
+            mirror
+        };
+        // Reflecting across its own axis fixes the centre and ideal point of
+        // the first but swaps its sides
+        let cases = [
+            (Pos::ORIGIN, mirror(Pos::ORIGIN, ideal.y.atan2(ideal.x))),
+            (Pos::new(0.2, 0.1), mirror(Pos::ORIGIN, 0.3)),
+            (Pos::new(0.2, 0.1), mirror(Pos::new(-0.3, 0.4), 2.)),
+        ];
+        for (cen, mirror) in cases {
+            let mut hypercycle = RotCircle::new(cen, 0.3, 5, curvature, false);
+            hypercycle.circle.kind = CircleKind::Hypercycle { ideal };
+            let reflected = mirror.rotate_circle(&hypercycle, false);
+            let transform = mirror.transform(1.);
+            assert!(transform.is_anti());
+            for i in -8..=8 {
+                for j in -8..=8 {
+                    let point = Pos::new(i as f64 / 10., j as f64 / 10.);
+                    let near_edge = hypercycle.circle.dist_to_boundary(&point) < 1e-6;
+                    if near_edge || point.dist(&Pos::ORIGIN) > 0.95 {
+                        continue;
+                    }
+                    assert_eq!(
+                        reflected.circle.contains(&transform.apply_to(point)),
+                        hypercycle.circle.contains(&point),
+                        "{point:?} about {cen:?} reflected by {mirror:?}",
+                    );
+                }
+            }
+        }
+    }
+}
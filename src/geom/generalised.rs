@@ -0,0 +1,139 @@
+use super::Pos;
+
+/// Radius of the disc drawn in place of a half-plane, large enough that its
+/// edge looks straight on screen.
+const HALF_PLANE_RADIUS: f64 = 1e4;
+
+/// A circle or straight line in the plane, as the zeros of the Hermitian
+/// form `a|z|² + bz̄ + b̄z + d` with `a` and `d` real. The form is negative
+/// inside, so the inside may be a disc, the outside of a circle or a
+/// half-plane.
+#[derive(Debug, Clone, Copy)]
+pub struct GeneralisedCircle {
+    pub a: f64,
+    pub b: Pos,
+    pub d: f64,
+}
+
+/// What the inside of a generalised circle looks like.
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    Disc {
+        cen: Pos,
+        rad: f64,
+    },
+    /// Everything outside a circle, including infinity
+    Exterior {
+        cen: Pos,
+        rad: f64,
+    },
+    /// The points on the side of the line through `point` facing away from
+    /// the unit vector `normal`
+    HalfPlane {
+        point: Pos,
+        normal: Pos,
+    },
+}
+
+impl GeneralisedCircle {
+    pub fn new(a: f64, b: Pos, d: f64) -> Self {
+        Self { a, b, d }
+    }
+
+    /// The disc centred on `cen` with radius `rad`.
+    pub fn disc(cen: Pos, rad: f64) -> Self {
+        Self::new(1., -cen, cen.dist_sq(&Pos::ORIGIN) - rad * rad)
+    }
+
+    /// The value of the form at `point`, negative inside.
+    pub fn eval(&self, point: Pos) -> f64 {
+        self.a * point.dist_sq(&Pos::ORIGIN) + 2. * (self.b * point.conjugate()).x + self.d
+    }
+
+    /// The same boundary with the inside and outside swapped.
+    pub fn complement(&self) -> Self {
+        Self::new(-self.a, -self.b, -self.d)
+    }
+
+    /// Reflects across the real axis.
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.a, self.b.conjugate(), self.d)
+    }
+
+    /// Scales the form to a size which won't overflow under repeated
+    /// transformation.
+    pub(super) fn normalised(&self) -> Self {
+        let size = self.a.abs() + self.b.dist(&Pos::ORIGIN) + self.d.abs();
+        if size == 0. || !size.is_finite() {
+            return *self;
+        }
+        Self::new(self.a / size, (1. / size) * self.b, self.d / size)
+    }
+
+    pub fn shape(&self) -> Shape {
+        let b_len = self.b.dist(&Pos::ORIGIN);
+        // Lines through infinity come out of transformations with `a` only
+        // nearly zero
+        if self.a.abs() <= 1e-12 * (b_len + self.d.abs()) {
+            let normal = (1. / b_len) * self.b;
+            return Shape::HalfPlane {
+                point: (-self.d / (2. * b_len)) * normal,
+                normal,
+            };
+        }
+        let cen = (-1. / self.a) * self.b;
+        let rad = ((b_len * b_len - self.a * self.d) / (self.a * self.a))
+            .max(0.)
+            .sqrt();
+        match self.a > 0. {
+            true => Shape::Disc { cen, rad },
+            false => Shape::Exterior { cen, rad },
+        }
+    }
+}
+
+impl Shape {
+    /// The disc, or `None` if the inside reaches infinity.
+    pub fn disc(self) -> Option<(Pos, f64)> {
+        match self {
+            Shape::Disc { cen, rad } => Some((cen, rad)),
+            _ => None,
+        }
+    }
+
+    /// Whether the insides of the two shapes certainly don't meet, treating
+    /// half-planes as in [`Self::fill_disc`].
+    pub fn disjoint(self, other: Shape) -> bool {
+        let (Some((c1, r1, out1)), Some((c2, r2, out2))) = (self.bounding(), other.bounding())
+        else {
+            return false;
+        };
+        let d = c1.dist(&c2);
+        match (out1, out2) {
+            (false, false) => d >= r1 + r2,
+            (false, true) => d + r1 <= r2,
+            (true, false) => d + r2 <= r1,
+            (true, true) => false,
+        }
+    }
+
+    /// The circle bounding the inside, and whether the inside is outside it.
+    fn bounding(self) -> Option<(Pos, f64, bool)> {
+        match self {
+            Shape::Exterior { cen, rad } => Some((cen, rad, true)),
+            _ => self.fill_disc().map(|(cen, rad)| (cen, rad, false)),
+        }
+    }
+
+    /// The inside as a disc, with a half-plane as a very large disc. An
+    /// exterior can't be drawn as a disc, so gives `None`.
+    pub fn fill_disc(self) -> Option<(Pos, f64)> {
+        match self {
+            Shape::Disc { cen, rad } => Some((cen, rad)),
+            Shape::Exterior { .. } => None,
+            Shape::HalfPlane { point, normal } => {
+                Some((point - HALF_PLANE_RADIUS * normal, HALF_PLANE_RADIUS))
+            }
+        }
+    }
+}
@@ -1,12 +1,37 @@
 use std::ops::Mul;
 
-use crate::geom::Pos;
+use serde::{Deserialize, Serialize};
+
+use crate::geom::{GeneralisedCircle, Pos};
 
 use crate::geom::Curvature;
 
-#[derive(Debug, Clone)]
-pub(crate) struct MobiusTransform {
+/// Tolerance for classifying transformations from their traces.
+const EPSILON: f64 = 1e-9;
+
+/// The kind of motion an orientation preserving Möbius transformation makes,
+/// read off from its trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MobiusClass {
+    Identity,
+    /// Turning about two fixed points, like a rotation of the sphere
+    Elliptic,
+    /// Sliding along the circles tangent at a single fixed point, like a
+    /// Euclidean translation
+    Parabolic,
+    /// Moving from one fixed point towards the other, possibly turning as
+    /// well, like a hyperbolic translation
+    Loxodromic,
+}
+
+/// A Möbius transformation `z ↦ (az + b) / (cz + d)`, or an anti-Möbius
+/// transformation `z ↦ (az̄ + b) / (cz̄ + d)` which also reverses orientation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MobiusTransform {
     transform: [[Pos; 2]; 2],
+    /// Whether the input is conjugated first
+    #[serde(default)]
+    anti: bool,
 }
 impl MobiusTransform {
     pub const IDENT: MobiusTransform = MobiusTransform {
@@ -14,57 +39,470 @@ impl MobiusTransform {
             [Pos::new(1., 0.), Pos::new(0., 0.)],
             [Pos::new(0., 0.), Pos::new(1., 0.)],
         ],
+        anti: false,
     };
 
     pub fn new(transform: [[Pos; 2]; 2]) -> Self {
-        Self { transform }
+        Self {
+            transform,
+            anti: false,
+        }
+    }
+
+    /// Reflection across the line through the origin at `angle` to the real
+    /// axis, `z ↦ exp(2i angle) z̄`.
+    pub fn reflection(angle: f64) -> Self {
+        Self {
+            transform: [
+                [
+                    Pos::new((2. * angle).cos(), (2. * angle).sin()),
+                    Pos::ORIGIN,
+                ],
+                [Pos::ORIGIN, Pos::new(1., 0.)],
+            ],
+            anti: true,
+        }
+    }
+
+    /// Rotation about `centre` by `angle` anticlockwise, scaled to determinant
+    /// 1.
+    pub fn rotation_about(centre: Pos, angle: f64, curvature: Curvature) -> Self {
+        let half = Pos::new((angle / 2.).cos(), (angle / 2.).sin());
+        let to_origin = Self::to_origin(centre, curvature);
+        let spin = Self::new([[half, Pos::ORIGIN], [Pos::ORIGIN, half.conjugate()]]);
+        // The determinant is that of `to_origin` squared, which is real and
+        // positive, so this is cheaper than `normalised_det`
+        let scale = 1. / to_origin.determinant().x;
+        let rotation = to_origin.inverse() * spin * to_origin;
+        Self::new(rotation.transform.map(|row| row.map(|x| scale * x)))
+    }
+
+    /// The isometry taking `from` to `to` along the geodesic between them,
+    /// without turning.
+    pub fn translation(from: Pos, to: Pos, curvature: Curvature) -> Self {
+        let to_origin = Self::to_origin(from, curvature);
+        let along = Self::to_origin(-to_origin.apply_to(to), curvature);
+        to_origin.inverse() * along * to_origin
+    }
+
+    /// An isometry of the space taking `point` to the origin.
+    pub fn to_origin(point: Pos, curvature: Curvature) -> Self {
+        let one = Pos::new(1., 0.);
+        let corner = curvature.model_coefficient() * point.conjugate();
+        Self::new([[one, -point], [corner, one]])
+    }
+
+    /// Whether the transformation reverses orientation.
+    pub fn is_anti(&self) -> bool {
+        self.anti
+    }
+
+    pub fn determinant(&self) -> Pos {
+        let [[a, b], [c, d]] = self.transform;
+        a * d - b * c
+    }
+
+    /// The same transformation with the matrix scaled to determinant 1,
+    /// which fixes it up to sign.
+    pub fn normalised_det(&self) -> Self {
+        let scale = complex_sqrt(self.determinant());
+        Self {
+            transform: self.transform.map(|row| row.map(|x| x / scale)),
+            anti: self.anti,
+        }
+    }
+
+    /// Trace of the matrix scaled to determinant 1, up to sign.
+    pub fn trace(&self) -> Pos {
+        let [[a, _], [_, d]] = self.normalised_det().transform;
+        a + d
+    }
+
+    /// The kind of motion, or `None` if the transformation reverses
+    /// orientation.
+    pub fn classify(&self) -> Option<MobiusClass> {
+        if self.anti {
+            return None;
+        }
+        let trace = self.trace();
+        let trace_sq = trace * trace;
+        let [[_, b], [c, _]] = self.normalised_det().transform;
+        let class = if trace_sq.dist(&Pos::new(4., 0.)) < EPSILON {
+            match b.dist(&Pos::ORIGIN) < EPSILON && c.dist(&Pos::ORIGIN) < EPSILON {
+                true => MobiusClass::Identity,
+                false => MobiusClass::Parabolic,
+            }
+        } else if trace_sq.y.abs() < EPSILON && (-EPSILON..4.).contains(&trace_sq.x) {
+            MobiusClass::Elliptic
+        } else {
+            MobiusClass::Loxodromic
+        };
+        Some(class)
+    }
+
+    /// The factor `k` the transformation multiplies by near a fixed point,
+    /// in coordinates where it is `w ↦ kw`, taken at the repelling fixed
+    /// point so that `|k| ≥ 1`. Parabolic transformations and the identity
+    /// have multiplier 1.
+    pub fn multiplier(&self) -> Pos {
+        // `k + 1/k` is the squared trace less 2
+        let trace = self.trace();
+        let sum = trace * trace - Pos::new(2., 0.);
+        let root = complex_sqrt(sum * sum - Pos::new(4., 0.));
+        let k = (0.5 * (sum + root), 0.5 * (sum - root));
+        match k.0.dist_sq(&Pos::ORIGIN) >= k.1.dist_sq(&Pos::ORIGIN) {
+            true => k.0,
+            false => k.1,
+        }
+    }
+
+    /// Angle turned about the fixed points, between 0 and π.
+    pub fn rotation_angle(&self) -> f64 {
+        let k = self.multiplier();
+        k.y.atan2(k.x).abs()
+    }
+
+    /// Distance moved along the geodesic between the fixed points, which is
+    /// only meaningful for isometries of hyperbolic space.
+    pub fn translation_length(&self) -> f64 {
+        self.multiplier().dist(&Pos::ORIGIN).ln()
+    }
+
+    /// The fixed points of an orientation preserving transformation, one for
+    /// parabolic transformations and two otherwise, with infinity given as
+    /// infinite coordinates. The identity fixes everything, and gives none.
+    pub fn fixed_points(&self) -> Vec<Pos> {
+        let class = self.classify();
+        if class.is_none() || class == Some(MobiusClass::Identity) {
+            return vec![];
+        }
+        // A double root, which rounding error would split in two
+        let parabolic = class == Some(MobiusClass::Parabolic);
+        let [[a, b], [c, d]] = self.normalised_det().transform;
+        // Solving `cz² + (d - a)z - b = 0`
+        if c.dist(&Pos::ORIGIN) < EPSILON {
+            let infinity = Pos::new(f64::INFINITY, f64::INFINITY);
+            return match parabolic {
+                true => vec![infinity],
+                false => vec![b / (d - a), infinity],
+            };
+        }
+        let root = complex_sqrt((d - a) * (d - a) + Pos::new(4., 0.) * b * c);
+        let two_c = Pos::new(2., 0.) * c;
+        match parabolic {
+            true => vec![(a - d) / two_c],
+            false => vec![(a - d + root) / two_c, (a - d - root) / two_c],
+        }
+    }
+
+    /// The transformation `other · self · other⁻¹`, which makes the same
+    /// motion about the points `other` moves the fixed points to.
+    pub fn conjugated_by(&self, other: &Self) -> Self {
+        other.clone() * self.clone() * other.inverse()
+    }
+
+    /// The kind of motion with its angle and distance, as shown in the app.
+    pub fn describe(&self) -> String {
+        let angle = self.rotation_angle().to_degrees();
+        match self.classify() {
+            None => "orientation reversing".to_string(),
+            Some(MobiusClass::Identity) => "identity".to_string(),
+            Some(MobiusClass::Elliptic) => format!("elliptic, turning {angle:.1}°"),
+            Some(MobiusClass::Parabolic) => "parabolic".to_string(),
+            Some(MobiusClass::Loxodromic) => format!(
+                "loxodromic, moving {:.3} and turning {angle:.1}°",
+                self.translation_length()
+            ),
+        }
     }
 
     pub fn apply_to(&self, pos: Pos) -> Pos {
         let [[a, b], [c, d]] = self.transform;
+        let pos = if self.anti { pos.conjugate() } else { pos };
         (a * pos + b) / (c * pos + d)
     }
 
+    /// The image of the tangent vector `direction` at `point`: multiplied by
+    /// the derivative `det / (cz + d)²`, after conjugating both for an anti
+    /// transformation.
+    pub fn apply_to_tangent(&self, point: Pos, direction: Pos) -> Pos {
+        let [[_, _], [c, d]] = self.transform;
+        let (point, direction) = match self.anti {
+            true => (point.conjugate(), direction.conjugate()),
+            false => (point, direction),
+        };
+        let denom = c * point + d;
+        self.determinant() * direction / (denom * denom)
+    }
+
+    /// The image of `circle`, which may be a circle or a line, with the image
+    /// of its inside as the new inside.
+    pub fn apply_to_circle(&self, circle: &GeneralisedCircle) -> GeneralisedCircle {
+        let h = match self.anti {
+            true => circle.conjugate(),
+            false => *circle,
+        };
+        // The image is `N* H N` for `N` the adjugate, which is a multiple of
+        // the inverse and so changes the form by a positive factor
+        let [[a, b], [c, d]] = self.transform;
+        let (col1, col2) = ((d, -c), (-b, a));
+        let form = |(u, v): (Pos, Pos), (x, y): (Pos, Pos)| {
+            u.conjugate() * (h.a * x + h.b * y) + v.conjugate() * (h.b.conjugate() * x + h.d * y)
+        };
+        GeneralisedCircle::new(form(col1, col1).x, form(col1, col2), form(col2, col2).x)
+            .normalised()
+    }
+
     pub fn inverse(&self) -> Self {
         let [[a, b], [c, d]] = self.transform;
-        Self::new([[d, -b], [-c, a]])
+        let inverse = [[d, -b], [-c, a]];
+        Self {
+            // Undoing `z ↦ M(z̄)` is `w ↦ conj(M⁻¹(w)) = conj(M⁻¹)(w̄)`
+            transform: match self.anti {
+                true => inverse.map(|row| row.map(Pos::conjugate)),
+                false => inverse,
+            },
+            anti: self.anti,
+        }
     }
 
+    /// Snaps the transformation back to an isometry of the space, against
+    /// rounding error built up by repeated camera moves.
     pub fn normalise(&mut self, curvature: Curvature) {
         let [[a, b], [c, d]] = &mut self.transform;
-        match curvature {
-            Curvature::Spherical => {
-                let scale = 1. / (a.dist_sq(&Pos::ORIGIN) + b.dist_sq(&Pos::ORIGIN));
-                *a = scale * *a;
-                *b = scale * *b;
-                *d = (a.dist(&Pos::ORIGIN) / d.dist(&Pos::ORIGIN)) * *d;
-                *c = -1. * *d * b.conjugate() / a.conjugate();
-            }
-            Curvature::Euclidean => {
-                *c = Pos::new(0., 0.);
-                *a = (1. / a.dist(&Pos::ORIGIN)) * *a;
-                *d = (1. / d.dist(&Pos::ORIGIN)) * *d;
-            }
-            Curvature::Hyperbolic => {
-                let scale = 1. / (a.dist_sq(&Pos::ORIGIN) + b.dist_sq(&Pos::ORIGIN));
-                *a = scale * *a;
-                *b = scale * *b;
-                *d = (a.dist(&Pos::ORIGIN) / d.dist(&Pos::ORIGIN)) * *d;
-                *c = *d * b.conjugate() / a.conjugate();
-            }
-        }
+        // Isometries are `[[a, b], [-κ d b̄ / ā, d]]` with `|d| = |a|`, for `κ`
+        // the model coefficient
+        let kappa = curvature.model_coefficient();
+        let scale = 1. / (a.dist_sq(&Pos::ORIGIN) + kappa.abs() * b.dist_sq(&Pos::ORIGIN)).sqrt();
+        *a = scale * *a;
+        *b = scale * *b;
+        *d = (a.dist(&Pos::ORIGIN) / d.dist(&Pos::ORIGIN)) * *d;
+        *c = -kappa * *d * b.conjugate() / a.conjugate();
     }
 }
 impl Mul for MobiusTransform {
     type Output = Self;
 
+    /// The transformation applying `rhs` and then `self`.
     fn mul(self, rhs: Self) -> Self::Output {
         let [[a, b], [c, d]] = self.transform;
-        let [[p, q], [r, s]] = rhs.transform;
+        // Conjugating the output of `rhs` conjugates its coefficients
+        let [[p, q], [r, s]] = match self.anti {
+            true => rhs.transform.map(|row| row.map(Pos::conjugate)),
+            false => rhs.transform,
+        };
 
-        Self::new([
-            [a * p + b * r, a * q + b * s],
-            [c * p + d * r, c * q + d * s],
-        ])
+        Self {
+            transform: [
+                [a * p + b * r, a * q + b * s],
+                [c * p + d * r, c * q + d * s],
+            ],
+            anti: self.anti ^ rhs.anti,
+        }
+    }
+}
+
+/// The square root with non-negative real part.
+fn complex_sqrt(z: Pos) -> Pos {
+    let r = z.dist(&Pos::ORIGIN).sqrt();
+    let theta = z.y.atan2(z.x) / 2.;
+    Pos::new(r * theta.cos(), r * theta.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{CircleKind, RotCircle, Shape};
+
+    /// Some maps of each kind, including orientation reversing ones.
+    fn examples() -> Vec<MobiusTransform> {
+        let plain = MobiusTransform::new([
+            [Pos::new(1., 0.5), Pos::new(-0.3, 0.2)],
+            [Pos::new(0.4, -0.1), Pos::new(0.9, 0.3)],
+        ]);
+        let rotation =
+            MobiusTransform::rotation_about(Pos::new(0.2, -0.4), 1.1, Curvature::SPHERICAL);
+        let reflection = MobiusTransform::reflection(0.7);
+        vec![
+            plain.clone(),
+            rotation.clone(),
+            reflection.clone(),
+            reflection.clone() * plain.clone(),
+            plain * reflection * rotation,
+        ]
+    }
+
+    /// Points on the edge of `circle`, each with points just off it on
+    /// either side.
+    fn sample(circle: &GeneralisedCircle) -> Vec<(Pos, Pos, Pos)> {
+        (0..12)
+            .map(|i| {
+                let t = i as f64 * 0.5 - 2.7;
+                match circle.shape() {
+                    Shape::Disc { cen, rad } | Shape::Exterior { cen, rad } => {
+                        let dir = Pos::new(t.cos(), t.sin());
+                        let edge = cen + rad * dir;
+                        (edge, edge - 1e-3 * dir, edge + 1e-3 * dir)
+                    }
+                    Shape::HalfPlane { point, normal } => {
+                        let edge = point + t * Pos::new(-normal.y, normal.x);
+                        (edge, edge - 1e-3 * normal, edge + 1e-3 * normal)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn circles_move_with_their_points() {
+        let disc = GeneralisedCircle::disc(Pos::new(0.3, -0.2), 0.7);
+        let line = GeneralisedCircle::new(0., Pos::new(0.6, 0.8), -0.4);
+        for circle in [disc, disc.complement(), line, line.complement()] {
+            for transform in examples() {
+                let image = transform.apply_to_circle(&circle);
+                for (edge, a, b) in sample(&circle) {
+                    let moved = [edge, a, b].map(|p| transform.apply_to(p));
+                    // Points sent near infinity can't be checked
+                    if moved.iter().any(|p| p.dist(&Pos::ORIGIN) > 1e3) {
+                        continue;
+                    }
+                    let scale = 1. + moved[0].dist_sq(&Pos::ORIGIN);
+                    assert!(image.eval(moved[0]).abs() < 1e-9 * scale);
+                    // The inside goes to the inside
+                    for (p, q) in [(a, moved[1]), (b, moved[2])] {
+                        assert_eq!(circle.eval(p) < 0., image.eval(q) < 0.);
+                    }
+                }
+            }
+        }
+    }
+
+    fn assert_near(a: Pos, b: Pos) {
+        assert!(a.dist(&b) < 1e-9, "{a:?} is not {b:?}");
+    }
+
+    #[test]
+    fn rotations_are_elliptic() {
+        let centre = Pos::new(0.3, -0.2);
+        for curvature in [
+            Curvature::SPHERICAL,
+            Curvature::EUCLIDEAN,
+            Curvature::HYPERBOLIC,
+        ] {
+            let rotation = MobiusTransform::rotation_about(centre, 1.2, curvature);
+            assert_eq!(rotation.classify(), Some(MobiusClass::Elliptic));
+            assert!((rotation.rotation_angle() - 1.2).abs() < 1e-9);
+            let fixed = rotation.fixed_points();
+            assert!(fixed.iter().any(|&p| p.dist(&centre) < 1e-9), "{fixed:?}");
+
+            // Moving the rotation moves its centre
+            let to = Pos::new(-0.1, 0.25);
+            let moved =
+                rotation.conjugated_by(&MobiusTransform::translation(centre, to, curvature));
+            assert_near(moved.apply_to(to), to);
+        }
+        assert_eq!(
+            MobiusTransform::IDENT.classify(),
+            Some(MobiusClass::Identity)
+        );
+        assert!(MobiusTransform::IDENT.fixed_points().is_empty());
+        assert_eq!(MobiusTransform::reflection(0.4).classify(), None);
+    }
+
+    #[test]
+    fn translations_and_horocycle_slides_are_parabolic() {
+        let (from, to) = (Pos::new(0.1, 0.2), Pos::new(-0.4, 0.5));
+        let translation = MobiusTransform::translation(from, to, Curvature::EUCLIDEAN);
+        assert_near(translation.apply_to(from), to);
+        assert_eq!(translation.classify(), Some(MobiusClass::Parabolic));
+        let fixed = translation.fixed_points();
+        assert!(fixed.len() == 1 && fixed[0].x.is_infinite(), "{fixed:?}");
+
+        let mut horocycle =
+            RotCircle::new(Pos::new(0.2, 0.1), 0.5, 5, Curvature::HYPERBOLIC, false);
+        horocycle.circle.kind = CircleKind::Horocycle {
+            ideal: Pos::new(0.6, 0.8),
+        };
+        let slide = horocycle.transform(1.);
+        assert_eq!(slide.classify(), Some(MobiusClass::Parabolic));
+        match slide.fixed_points()[..] {
+            [fixed] => assert!(fixed.dist(&Pos::new(0.6, 0.8)) < 1e-6, "{fixed:?}"),
+            ref fixed => panic!("{fixed:?}"),
+        }
+    }
+
+    #[test]
+    fn hyperbolic_translations_are_loxodromic() {
+        let (from, to) = (Pos::new(0.1, 0.2), Pos::new(-0.4, 0.5));
+        let translation = MobiusTransform::translation(from, to, Curvature::HYPERBOLIC);
+        assert_eq!(translation.classify(), Some(MobiusClass::Loxodromic));
+        let distance = from.dist_in_space(&to, Curvature::HYPERBOLIC);
+        assert!((translation.translation_length() - distance).abs() < 1e-9);
+        assert!(translation.rotation_angle() < 1e-6);
+        // The fixed points are the ends of the geodesic, on the boundary
+        for fixed in translation.fixed_points() {
+            assert!((fixed.dist(&Pos::ORIGIN) - 1.).abs() < 1e-9, "{fixed:?}");
+        }
+
+        // `z ↦ 4 exp(iθ) z`
+        let half = Pos::new(0.5_f64.cos(), 0.5_f64.sin());
+        let spiral = MobiusTransform::new([
+            [Pos::new(2., 0.) * half, Pos::ORIGIN],
+            [Pos::ORIGIN, Pos::new(0.5, 0.) * half.conjugate()],
+        ]);
+        assert_eq!(spiral.classify(), Some(MobiusClass::Loxodromic));
+        assert!((spiral.translation_length() - 4_f64.ln()).abs() < 1e-9);
+        assert!((spiral.rotation_angle() - 1.).abs() < 1e-9);
+        let fixed = spiral.fixed_points();
+        assert!(
+            fixed.iter().any(|p| p.dist(&Pos::ORIGIN) < 1e-9),
+            "{fixed:?}"
+        );
+        assert!(fixed.iter().any(|p| p.x.is_infinite()), "{fixed:?}");
+    }
+
+    #[test]
+    fn rotation_matches_closed_form() {
+        let centre = Pos::new(0.3, -0.2);
+        let point = Pos::new(-0.1, 0.45);
+        for theta in [0.4_f64, 2., -1.3] {
+            let phi = Pos::new((theta / 2.).cos(), (theta / 2.).sin());
+            let r2 = centre.dist_sq(&Pos::ORIGIN);
+
+            // The formulas used before rotations were built from matrices
+            let a = phi + r2 * phi.conjugate();
+            let b = Pos::new(0., -2. * phi.y) * centre.conjugate();
+            let spherical = (a * point - b.conjugate()) / (b * point + a.conjugate());
+            let a = phi - r2 * phi.conjugate();
+            let b = Pos::new(0., 2. * phi.y) * centre.conjugate();
+            let hyperbolic = (a * point + b.conjugate()) / (b * point + a.conjugate());
+            let (x, y) = (point.x - centre.x, point.y - centre.y);
+            let euclidean = Pos::new(
+                theta.cos() * x + theta.sin() * y + centre.x,
+                theta.cos() * y - theta.sin() * x + centre.y,
+            );
+
+            let rotate = |curvature| MobiusTransform::rotation_about(centre, theta, curvature);
+            assert_near(rotate(Curvature::SPHERICAL).apply_to(point), spherical);
+            assert_near(rotate(Curvature::HYPERBOLIC).apply_to(point), hyperbolic);
+            // The flat formula turned clockwise
+            let flat = MobiusTransform::rotation_about(centre, -theta, Curvature::EUCLIDEAN);
+            assert_near(flat.apply_to(point), euclidean);
+        }
+    }
+
+    #[test]
+    fn tangent_matches_difference() {
+        const EPSILON: f64 = 1e-7;
+        let point = Pos::new(0.3, 0.1);
+        for transform in examples() {
+            for angle in [0_f64, 1., 2.5] {
+                let direction = Pos::new(angle.cos(), angle.sin());
+                let exact = transform.apply_to_tangent(point, direction);
+                let moved = transform.apply_to(point + EPSILON * direction);
+                let approx = (1. / EPSILON) * (moved - transform.apply_to(point));
+                assert!(exact.dist(&approx) < 1e-5, "{exact:?} vs {approx:?}");
+            }
+        }
     }
 }
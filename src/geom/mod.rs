@@ -1,15 +1,176 @@
+use serde::{Deserialize, Serialize};
+
 mod circles;
+mod generalised;
 mod mobius;
 mod points;
 
-pub(crate) use circles::{Circle, GraphicsCircle, RotCircle};
-pub(crate) use mobius::MobiusTransform;
-pub(crate) use points::Pos;
+pub use circles::{Circle, CircleKind, GraphicsCircle, RotCircle, Turn};
+pub use generalised::{GeneralisedCircle, Shape};
+pub use mobius::{MobiusClass, MobiusTransform};
+pub use points::Pos;
+
+/// Curvatures closer to zero than this are treated as flat, where the
+/// formulas for curved space divide by zero.
+const FLAT: f64 = 1e-9;
+
+/// The Gaussian curvature `K` of the space: positive for a sphere of radius
+/// `1/√K`, zero for the Euclidean plane and negative for a hyperbolic plane.
+///
+/// Every space is drawn in the conformal model with metric
+/// `2λ|dz| / (1 + Kλ²|z|²)`, where `λ = (1 + K²) / 2`. At curvatures 1, 0
+/// and -1 this is the stereographic projection of the unit sphere, the plane
+/// itself and the Poincaré disk, and in between it changes smoothly, so that
+/// a puzzle can be morphed from one to another.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CurvatureRepr", into = "CurvatureRepr")]
+pub struct Curvature(f64);
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
-pub enum Curvature {
+/// How curvatures are saved, with the named ones as they always have been.
+#[derive(Serialize, Deserialize)]
+enum CurvatureRepr {
     Spherical,
-    #[default]
     Euclidean,
     Hyperbolic,
+    Other(f64),
+}
+impl From<CurvatureRepr> for Curvature {
+    fn from(value: CurvatureRepr) -> Self {
+        match value {
+            CurvatureRepr::Spherical => Curvature::SPHERICAL,
+            CurvatureRepr::Euclidean => Curvature::EUCLIDEAN,
+            CurvatureRepr::Hyperbolic => Curvature::HYPERBOLIC,
+            CurvatureRepr::Other(k) => Curvature::new(k),
+        }
+    }
+}
+impl From<Curvature> for CurvatureRepr {
+    fn from(value: Curvature) -> Self {
+        if value == Curvature::SPHERICAL {
+            CurvatureRepr::Spherical
+        } else if value == Curvature::EUCLIDEAN {
+            CurvatureRepr::Euclidean
+        } else if value == Curvature::HYPERBOLIC {
+            CurvatureRepr::Hyperbolic
+        } else {
+            CurvatureRepr::Other(value.0)
+        }
+    }
+}
+
+impl Curvature {
+    pub const SPHERICAL: Curvature = Curvature(1.);
+    pub const EUCLIDEAN: Curvature = Curvature(0.);
+    pub const HYPERBOLIC: Curvature = Curvature(-1.);
+
+    pub const fn new(k: f64) -> Self {
+        Self(k)
+    }
+
+    /// The Gaussian curvature `K`.
+    pub fn k(self) -> f64 {
+        self.0
+    }
+
+    pub fn is_spherical(self) -> bool {
+        self.0 >= FLAT
+    }
+
+    pub fn is_hyperbolic(self) -> bool {
+        self.0 <= -FLAT
+    }
+
+    pub fn name(self) -> &'static str {
+        if self.is_spherical() {
+            "Spherical"
+        } else if self.is_hyperbolic() {
+            "Hyperbolic"
+        } else {
+            "Euclidean"
+        }
+    }
+
+    /// The factor `λ` by which the model is scaled at the origin.
+    fn scale(self) -> f64 {
+        (1. + self.0 * self.0) / 2.
+    }
+
+    /// The coefficient `Kλ²` of `|z|²` in the metric, which is also the
+    /// corner of the matrix moving a point to the origin.
+    pub(crate) fn model_coefficient(self) -> f64 {
+        self.0 * self.scale().powi(2)
+    }
+
+    /// Distance in space from the origin to a point at `r` from it in the
+    /// model.
+    pub fn dist_from_origin(self, r: f64) -> f64 {
+        let root = self.0.abs().sqrt();
+        let lambda = self.scale();
+        if self.is_spherical() {
+            2. / root * (lambda * root * r).atan()
+        } else if self.is_hyperbolic() {
+            2. / root * (lambda * root * r).atanh()
+        } else {
+            2. * lambda * r
+        }
+    }
+
+    /// Distance in the model from the origin to a point `distance` from it
+    /// in space, the inverse of [`Self::dist_from_origin`].
+    pub fn model_radius(self, distance: f64) -> f64 {
+        let root = self.0.abs().sqrt();
+        let lambda = self.scale();
+        if self.is_spherical() {
+            (root * distance / 2.).tan() / (lambda * root)
+        } else if self.is_hyperbolic() {
+            (root * distance / 2.).tanh() / (lambda * root)
+        } else {
+            distance / (2. * lambda)
+        }
+    }
+
+    /// The points less than `rad` from the origin. This is written so as to
+    /// stay accurate for spherical discs nearly covering the sphere, whose
+    /// radius in the model is huge.
+    pub fn origin_disc(self, rad: f64) -> GeneralisedCircle {
+        let root = self.0.abs().sqrt();
+        let half = root * rad / 2.;
+        let (cos, sin) = if self.is_spherical() {
+            (half.cos(), half.sin())
+        } else if self.is_hyperbolic() {
+            (half.cosh(), half.sinh())
+        } else {
+            return GeneralisedCircle::disc(Pos::ORIGIN, self.model_radius(rad));
+        };
+        let cos = self.scale() * root * cos;
+        GeneralisedCircle::new(cos * cos, Pos::ORIGIN, -sin * sin)
+    }
+
+    /// Radius of the disk modelling hyperbolic space, which is infinite for
+    /// the others.
+    pub fn model_bound(self) -> f64 {
+        match self.is_hyperbolic() {
+            true => 1. / (-self.model_coefficient()).sqrt(),
+            false => f64::INFINITY,
+        }
+    }
+
+    /// Distance between opposite points of a sphere, or infinity.
+    pub fn antipodal_dist(self) -> f64 {
+        match self.is_spherical() {
+            true => std::f64::consts::PI / self.0.sqrt(),
+            false => f64::INFINITY,
+        }
+    }
+
+    /// Largest useful circle radius, used as the range of radius sliders.
+    pub fn max_radius(self) -> f64 {
+        if self.is_spherical() {
+            self.antipodal_dist().min(4.)
+        } else if self.is_hyperbolic() {
+            4.
+        } else {
+            2.
+        }
+    }
 }
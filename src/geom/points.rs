@@ -1,11 +1,12 @@
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use eframe::egui::Pos2;
+use serde::{Deserialize, Serialize};
 
 use super::Curvature;
 
-#[derive(Debug, Default, Copy, Clone)]
-pub(crate) struct Pos {
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Pos {
     pub x: f64,
     pub y: f64,
 }
@@ -43,26 +44,20 @@ impl Pos {
     }
 
     pub fn dist_in_space(self, other: &Pos, curvature: Curvature) -> f64 {
-        match curvature {
-            Curvature::Spherical => {
-                let a = (*other - self) / (self.conjugate() * *other + Pos::new(1., 0.));
-                2. * a.dist(&Pos::ORIGIN).atan()
-            }
-            Curvature::Euclidean => self.dist(other),
-            Curvature::Hyperbolic => {
-                let a = (*other - self) / (-self.conjugate() * *other + Pos::new(1., 0.));
-                2. * a.dist(&Pos::ORIGIN).atanh()
-            }
+        if curvature == Curvature::EUCLIDEAN {
+            return self.dist(other);
         }
-    }
-    /// Only for spherical inverted circles
-    pub fn dist_to_inf(&self, curvature: Curvature) -> f64 {
-        match curvature {
-            Curvature::Spherical => {
-                let a = 1. / self.dist(&Pos::ORIGIN);
-                2. * a.atan() * 0.8
-            }
-            _ => f64::INFINITY,
+        // Move `self` to the origin, as `MobiusTransform::to_origin` does
+        let corner = curvature.model_coefficient() * self.conjugate();
+        let a = (*other - self) / (corner * *other + Pos::new(1., 0.));
+        curvature.dist_from_origin(a.dist(&Pos::ORIGIN))
+    }
+    /// Whether the point lies in the model of the space, i.e. inside the
+    /// disk for hyperbolic space.
+    pub fn is_in_space(&self, curvature: Curvature) -> bool {
+        match curvature.is_hyperbolic() {
+            true => self.dist(&Pos::ORIGIN) < curvature.model_bound(),
+            false => self.x.is_finite() && self.y.is_finite(),
         }
     }
 
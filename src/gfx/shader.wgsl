@@ -4,6 +4,7 @@ struct UvVertex {
     @location(2) col: vec4<f32>,
     @location(3) centre: vec2<f32>,
     @location(4) scale: vec2<f32>,
+    @location(5) inverted: f32,
 }
 
 @group(0) @binding(1) var blit_src_texture: texture_2d<f32>;
@@ -44,10 +45,17 @@ fn vertex (
     uv_vertex: UvVertex,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.position = vec4(uv_vertex.centre + uv_vertex.scale*uv_vertex.position,0.,1.);
+    if uv_vertex.inverted > 0.5 {
+        // Cover the whole target, measuring offsets in units of the radius
+        out.position = vec4(uv_vertex.position,0.,1.);
+        out.offset = (uv_vertex.position - uv_vertex.centre) / uv_vertex.scale;
+    } else {
+        out.position = vec4(uv_vertex.centre + uv_vertex.scale*uv_vertex.position,0.,1.);
+        out.offset = uv_vertex.offset;
+    }
     out.col = uv_vertex.col;
-    out.offset = uv_vertex.offset;
     out.idx = idx;
+    out.inverted = uv_vertex.inverted;
     return out;
 }
 
@@ -56,13 +64,14 @@ struct VertexOutput {
     @location(0) col: vec4<f32>,
     @location(1) offset: vec2<f32>,
     @location(2) idx: u32,
+    @location(3) inverted: f32,
 }
 
 @fragment
 fn fragment (
     in: VertexOutput
 ) -> @location(0) vec4<f32> {
-    if dot(in.offset,in.offset) <= 1. {
+    if (dot(in.offset,in.offset) <= 1.) != (in.inverted > 0.5) {
         return in.col;
     }
     discard;
@@ -1,6 +1,7 @@
 //! Structs shared between the CPU and GPU (vertices, uniforms, etc.).
 
 use eframe::wgpu;
+use pentagon_gardening_simulator::GraphicsCircle;
 
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, PartialEq, bytemuck::NoUninit, bytemuck::Zeroable)]
@@ -41,10 +42,25 @@ pub struct CircleInstance {
     pub col: [f32; 4],
     pub centre: [f32; 2],
     pub scale: [f32; 2],
+    /// 1 to fill outside the circle, covering the whole target, or 0
+    pub inverted: f32,
 }
 impl CircleInstance {
-    pub const fn new(centre: [f32; 2], scale: [f32; 2], col: [f32; 4]) -> Self {
-        Self { centre, scale, col }
+    pub const fn new(centre: [f32; 2], scale: [f32; 2], col: [f32; 4], inverted: bool) -> Self {
+        Self {
+            centre,
+            scale,
+            col,
+            inverted: inverted as u8 as f32,
+        }
+    }
+    pub fn from_graphics_circle(circle: &GraphicsCircle, scale: [f32; 2]) -> Self {
+        Self::new(
+            [circle.centre[0] * scale[0], circle.centre[1] * scale[1]],
+            [scale[0] * circle.radius, scale[1] * circle.radius],
+            circle.col,
+            circle.inverted,
+        )
     }
     pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
         array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
@@ -53,6 +69,7 @@ impl CircleInstance {
             2 => Float32x4,
             3 => Float32x2,
             4 => Float32x2,
+            5 => Float32,
         ],
     };
 }
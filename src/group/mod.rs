@@ -0,0 +1,15 @@
+mod permutation;
+mod stabchain;
+
+pub use permutation::Permutation;
+pub use stabchain::StabChain;
+
+/// How a permutation group compares to the full symmetric group on the
+/// points it acts on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GroupKind {
+    Trivial,
+    Symmetric,
+    Alternating,
+    Other,
+}
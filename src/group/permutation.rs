@@ -0,0 +1,135 @@
+use itertools::Itertools;
+
+/// A permutation of `0..n`, stored as the image of each point.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Permutation {
+    images: Vec<u32>,
+}
+impl Permutation {
+    pub fn identity(n: usize) -> Self {
+        Self {
+            images: (0..n as u32).collect(),
+        }
+    }
+
+    /// Returns `None` if `images` is not a permutation of `0..images.len()`.
+    pub fn from_images(images: Vec<u32>) -> Option<Self> {
+        let mut seen = vec![false; images.len()];
+        for &i in &images {
+            if std::mem::replace(seen.get_mut(i as usize)?, true) {
+                return None;
+            }
+        }
+        Some(Self { images })
+    }
+
+    pub fn images(&self) -> &[u32] {
+        &self.images
+    }
+
+    /// Number of points acted on.
+    pub fn degree(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn apply(&self, point: usize) -> usize {
+        self.images[point] as usize
+    }
+
+    /// The permutation which applies `self` and then `other`.
+    pub fn then(&self, other: &Self) -> Self {
+        Self {
+            images: self
+                .images
+                .iter()
+                .map(|&i| other.images[i as usize])
+                .collect(),
+        }
+    }
+
+    /// Replaces `self` with `self.then(other)` without allocating.
+    pub fn then_assign(&mut self, other: &Self) {
+        for i in &mut self.images {
+            *i = other.images[*i as usize];
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut images = vec![0; self.images.len()];
+        for (i, &j) in self.images.iter().enumerate() {
+            images[j as usize] = i as u32;
+        }
+        Self { images }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.images
+            .iter()
+            .enumerate()
+            .all(|(i, &j)| i == j as usize)
+    }
+
+    /// Points which are not fixed.
+    pub fn support(&self) -> impl Iterator<Item = usize> + '_ {
+        self.images
+            .iter()
+            .enumerate()
+            .filter(|(i, &j)| *i != j as usize)
+            .map(|(i, _)| i)
+    }
+
+    /// Nontrivial cycles, each starting from its smallest point.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.images.len()];
+        let mut cycles = vec![];
+        for start in 0..self.images.len() {
+            if seen[start] {
+                continue;
+            }
+            let mut cycle = vec![];
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                cycle.push(i);
+                i = self.apply(i);
+            }
+            if cycle.len() > 1 {
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    /// Smallest number of times the permutation must be applied to give
+    /// the identity.
+    pub fn order(&self) -> usize {
+        self.cycles()
+            .iter()
+            .map(|c| c.len())
+            .fold(1, |a, b| a / gcd(a, b) * b)
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.cycles().iter().map(|c| c.len() - 1).sum::<usize>() % 2 == 0
+    }
+
+    /// The action on `points`, reindexed by position in `points`. `points`
+    /// must be closed under the permutation.
+    pub fn restrict(&self, points: &[usize]) -> Self {
+        let index = points
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| (p, i as u32))
+            .collect::<std::collections::HashMap<_, _>>();
+        Self {
+            images: points.iter().map(|&p| index[&self.apply(p)]).collect_vec(),
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
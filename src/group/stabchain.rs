@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{GroupKind, Permutation};
+
+#[derive(Debug, Clone)]
+struct Level {
+    base: usize,
+    /// Strong generators introduced at this level
+    gens: Vec<Permutation>,
+    /// For each point in the orbit of `base`, the inverse of a permutation
+    /// taking `base` to it
+    transversal: HashMap<usize, Permutation>,
+}
+
+/// Base and strong generating set of a permutation group, built with
+/// Knuth's incremental Schreier–Sims.
+#[derive(Debug, Clone)]
+pub struct StabChain {
+    degree: usize,
+    levels: Vec<Level>,
+}
+impl StabChain {
+    pub fn new(degree: usize, generators: &[Permutation]) -> Self {
+        let mut ret = Self {
+            degree,
+            levels: vec![],
+        };
+        for g in generators {
+            ret.add(0, g.clone());
+        }
+        ret
+    }
+
+    /// Removes as much of `perm` as possible using the levels from `level`
+    /// down, returning what is left.
+    fn sift(&self, level: usize, mut perm: Permutation) -> Permutation {
+        for l in &self.levels[level..] {
+            let image = perm.apply(l.base);
+            if image == l.base {
+                continue;
+            }
+            let Some(rep_inv) = l.transversal.get(&image) else {
+                break;
+            };
+            perm.then_assign(rep_inv);
+        }
+        perm
+    }
+
+    pub fn contains(&self, perm: &Permutation) -> bool {
+        self.sift(0, perm.clone()).is_identity()
+    }
+
+    /// Adds `perm`, which must fix the base points above `level`, as a strong
+    /// generator unless it is already in the group.
+    fn add(&mut self, level: usize, perm: Permutation) {
+        if self.sift(level, perm.clone()).is_identity() {
+            return;
+        }
+        if level == self.levels.len() {
+            let base = perm.support().next().expect("Identity permutation");
+            self.levels.push(Level {
+                base,
+                gens: vec![],
+                transversal: HashMap::from([(base, Permutation::identity(self.degree))]),
+            });
+        }
+        self.levels[level].gens.push(perm.clone());
+        let reps = self.levels[level]
+            .transversal
+            .values()
+            .map(|rep_inv| rep_inv.inverse())
+            .collect::<Vec<_>>();
+        for rep in reps {
+            self.extend(level, rep.then(&perm));
+        }
+    }
+
+    fn extend(&mut self, level: usize, perm: Permutation) {
+        let mut stack = vec![perm];
+        while let Some(perm) = stack.pop() {
+            let image = perm.apply(self.levels[level].base);
+            if let Some(rep_inv) = self.levels[level].transversal.get(&image) {
+                let schreier = perm.then(rep_inv);
+                self.add(level + 1, schreier);
+            } else {
+                stack.extend(self.levels[level].gens.iter().map(|g| perm.then(g)));
+                self.levels[level].transversal.insert(image, perm.inverse());
+            }
+        }
+    }
+
+    /// Lengths of the basic orbits, whose product is the group order.
+    pub fn orbit_lengths(&self) -> Vec<usize> {
+        self.levels.iter().map(|l| l.transversal.len()).collect()
+    }
+
+    /// The group order, or `None` if it does not fit in a `u128`.
+    pub fn order(&self) -> Option<u128> {
+        self.orbit_lengths()
+            .into_iter()
+            .try_fold(1_u128, |acc, len| acc.checked_mul(len as u128))
+    }
+
+    pub fn log10_order(&self) -> f64 {
+        self.orbit_lengths()
+            .into_iter()
+            .map(|len| (len as f64).log10())
+            .sum()
+    }
+
+    /// Compares the group to the symmetric group on all `degree` points,
+    /// exactly, by comparing prime factorisations of the orders.
+    pub fn kind(&self) -> GroupKind {
+        if self.levels.is_empty() {
+            return GroupKind::Trivial;
+        }
+        let order = prime_exponents(self.orbit_lengths());
+        let mut factorial = prime_exponents(2..=self.degree);
+        if order == factorial {
+            return GroupKind::Symmetric;
+        }
+        if let Some(twos) = factorial.get_mut(&2) {
+            *twos -= 1;
+            if *twos == 0 {
+                factorial.remove(&2);
+            }
+        }
+        if order == factorial {
+            return GroupKind::Alternating;
+        }
+        GroupKind::Other
+    }
+}
+
+fn prime_exponents(factors: impl IntoIterator<Item = usize>) -> BTreeMap<usize, u32> {
+    let mut ret = BTreeMap::new();
+    for mut n in factors {
+        let mut p = 2;
+        while n > 1 {
+            while n % p == 0 {
+                *ret.entry(p).or_default() += 1;
+                n /= p;
+            }
+            p += 1;
+            if p * p > n && n > 1 {
+                *ret.entry(n).or_default() += 1;
+                break;
+            }
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The permutation of `0..degree` with the given cycles, numbered from 1
+    /// as puzzle facelets usually are.
+    fn from_cycles(degree: usize, cycles: &[&[u32]]) -> Permutation {
+        let mut images = (0..degree as u32).collect::<Vec<_>>();
+        for cycle in cycles {
+            for (i, &p) in cycle.iter().enumerate() {
+                images[p as usize - 1] = cycle[(i + 1) % cycle.len()] - 1;
+            }
+        }
+        Permutation::from_images(images).expect("Cycles overlap")
+    }
+
+    #[test]
+    fn symmetric_group() {
+        let gens = [
+            from_cycles(6, &[&[1, 2]]),
+            from_cycles(6, &[&[1, 2, 3, 4, 5, 6]]),
+        ];
+        let chain = StabChain::new(6, &gens);
+        assert_eq!(chain.order(), Some(720));
+        assert_eq!(chain.kind(), GroupKind::Symmetric);
+    }
+
+    #[test]
+    fn alternating_group() {
+        let gens = [
+            from_cycles(5, &[&[1, 2, 3]]),
+            from_cycles(5, &[&[2, 3, 4]]),
+            from_cycles(5, &[&[3, 4, 5]]),
+        ];
+        let chain = StabChain::new(5, &gens);
+        assert_eq!(chain.order(), Some(60));
+        assert_eq!(chain.kind(), GroupKind::Alternating);
+        assert!(chain.contains(&from_cycles(5, &[&[1, 2], &[3, 4]])));
+        assert!(!chain.contains(&from_cycles(5, &[&[1, 2]])));
+    }
+
+    #[test]
+    fn dihedral_group() {
+        let gens = [from_cycles(4, &[&[1, 2, 3, 4]]), from_cycles(4, &[&[2, 4]])];
+        let chain = StabChain::new(4, &gens);
+        assert_eq!(chain.order(), Some(8));
+        assert_eq!(chain.kind(), GroupKind::Other);
+    }
+
+    #[test]
+    fn trivial_group() {
+        let chain = StabChain::new(3, &[Permutation::identity(3)]);
+        assert_eq!(chain.order(), Some(1));
+        assert_eq!(chain.kind(), GroupKind::Trivial);
+    }
+
+    #[test]
+    fn rubiks_cube() {
+        let gens = [
+            from_cycles(
+                48,
+                &[
+                    &[1, 3, 8, 6],
+                    &[2, 5, 7, 4],
+                    &[9, 33, 25, 17],
+                    &[10, 34, 26, 18],
+                    &[11, 35, 27, 19],
+                ],
+            ),
+            from_cycles(
+                48,
+                &[
+                    &[9, 11, 16, 14],
+                    &[10, 13, 15, 12],
+                    &[1, 17, 41, 40],
+                    &[4, 20, 44, 37],
+                    &[6, 22, 46, 35],
+                ],
+            ),
+            from_cycles(
+                48,
+                &[
+                    &[17, 19, 24, 22],
+                    &[18, 21, 23, 20],
+                    &[6, 25, 43, 16],
+                    &[7, 28, 42, 13],
+                    &[8, 30, 41, 11],
+                ],
+            ),
+            from_cycles(
+                48,
+                &[
+                    &[25, 27, 32, 30],
+                    &[26, 29, 31, 28],
+                    &[3, 38, 43, 19],
+                    &[5, 36, 45, 21],
+                    &[8, 33, 48, 24],
+                ],
+            ),
+            from_cycles(
+                48,
+                &[
+                    &[33, 35, 40, 38],
+                    &[34, 37, 39, 36],
+                    &[3, 9, 46, 32],
+                    &[2, 12, 47, 29],
+                    &[1, 14, 48, 27],
+                ],
+            ),
+            from_cycles(
+                48,
+                &[
+                    &[41, 43, 48, 46],
+                    &[42, 45, 47, 44],
+                    &[14, 22, 30, 38],
+                    &[15, 23, 31, 39],
+                    &[16, 24, 32, 40],
+                ],
+            ),
+        ];
+        let chain = StabChain::new(48, &gens);
+        assert_eq!(chain.order(), Some(43_252_003_274_489_856_000));
+    }
+}
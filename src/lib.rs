@@ -0,0 +1,19 @@
+//! Puzzle logic for Pentagon Gardening Simulator, usable without opening a
+//! window.
+
+pub mod config;
+pub mod geom;
+pub mod group;
+pub mod puzzle;
+pub mod render;
+pub mod svg;
+pub mod sweep;
+
+pub use geom::{
+    Circle, CircleKind, Curvature, GeneralisedCircle, GraphicsCircle, MobiusClass, MobiusTransform,
+    Pos, RotCircle, Shape, Turn,
+};
+pub use puzzle::{
+    Algorithm, Explorer, Grip, GripSet, Move, Orbit, OrbitClass, Piece, PieceGroup,
+    PuzzleDefinition, PuzzleState, Region, Twist,
+};
@@ -1,16 +1,21 @@
-use std::sync::Arc;
+use std::f64::consts::TAU;
+use std::sync::{mpsc, Arc};
 
 use bitvec::prelude::*;
-use eframe::egui::{self, pos2, vec2, Pos2, Vec2};
-
-mod geom;
-use geom::{Circle, Curvature, GraphicsCircle, MobiusTransform, Pos, RotCircle};
-mod puzzle;
+use eframe::egui::{self, pos2, Pos2};
+use itertools::Itertools;
+use pentagon_gardening_simulator::{
+    config::{Configuration, MoveLog},
+    group::GroupKind,
+    render, svg,
+    sweep::{Sweep, SweepCell},
+    Algorithm, Circle, CircleKind, Curvature, Explorer, GraphicsCircle, Grip, MobiusTransform,
+    Move, Orbit, OrbitClass, Piece, PieceGroup, Pos, PuzzleDefinition, PuzzleState, RotCircle,
+    Shape, Twist,
+};
 
 mod gfx;
-use gfx::GraphicsState;
-use itertools::Itertools;
-use puzzle::{Grip, Piece};
+use gfx::{CircleInstance, GraphicsState};
 
 fn main() -> eframe::Result<()> {
     let native_options = eframe::NativeOptions {
@@ -24,51 +29,73 @@ fn main() -> eframe::Result<()> {
     )
 }
 
-fn gen_circles(N: usize, distance: f64, curvature: Curvature) -> Vec<RotCircle> {
-    let ang = std::f64::consts::TAU / N as f64;
-    let angs = (0..N).map(|n| n as f64 * ang).collect_vec();
-    let distance = match curvature {
-        Curvature::Spherical => (distance / 4.).tan(),
-        Curvature::Euclidean => distance / 2.,
-        Curvature::Hyperbolic => (distance / 4.).tanh(),
-    };
-    angs.iter()
-        .map(|ang| {
-            RotCircle::new(
-                distance * Pos::new(-ang.cos(), ang.sin()),
-                // Pos::new(0., 0.),
-                0.5,
-                5,
-                curvature,
-                false,
-            )
-        })
-        .collect_vec()
-}
+/// Positions the solver may expand before giving up.
+const SOLVE_NODES: usize = 1_000_000;
+
+/// Number of commutators to list.
+const COMMUTATOR_COUNT: usize = 10;
+
+/// Number of classified orbits to remember for hovering over again.
+const ORBIT_CLASS_CACHE: usize = 64;
+
+/// The moves being solved, and the solution if one was found.
+type SolveResult = (Vec<Move>, Option<Vec<Move>>);
+
 fn gen_colors(i: usize) -> egui::Color32 {
-    if let Some(col) = colorous::SET1.get(i) {
-        return egui::Color32::from_rgb(col.r, col.g, col.b);
+    let [r, g, b] = render::generator_colour(i);
+    egui::Color32::from_rgb(r, g, b)
+}
+
+fn group_summary(group: &PieceGroup) -> String {
+    let format_order = |chain: &pentagon_gardening_simulator::group::StabChain| match chain.order()
+    {
+        Some(order) => order.to_string(),
+        None => format!("~1e{:.1}", chain.log10_order()),
     };
-    return egui::Color32::GOLD;
+    let mut ret = format!(
+        "{} pieces in {} orbits, group order {}",
+        group.pieces.len(),
+        group.orbits.len(),
+        format_order(&group.chain)
+    );
+    for (i, orbit) in group.orbits.iter().enumerate() {
+        let n = orbit.len();
+        ret += &match group.orbit_kind(i) {
+            GroupKind::Trivial => format!("\nOrbit of {n}: trivial"),
+            GroupKind::Symmetric => format!("\nOrbit of {n}: S{n}"),
+            GroupKind::Alternating => format!("\nOrbit of {n}: A{n}"),
+            GroupKind::Other => format!(
+                "\nOrbit of {n}: order {}",
+                format_order(&group.orbit_chain(i))
+            ),
+        };
+    }
+    ret
+}
+
+/// Which sweep result the heat map shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SweepMetric {
+    MaxOrbit,
+    OrbitCount,
 }
 
 struct PieceData {
     grip_count: u32,
     orbit_size: u32,
+    /// Depth at which the orbit search gave up, if it did
+    truncated_at: Option<u32>,
 }
 
 struct App {
     gfx: Arc<GraphicsState>,
-    /// Transformation circles
-    circles: Vec<RotCircle>,
+    explorer: Explorer,
     scale: f32,
-    depth: u32,
     grip_rad: f32,
     grip_cuts: bool,
     autofill: bool,
     index: usize,
     pixel_mask: BitBox,
-    curvature: Curvature,
     circle_distance: f64,
     circle_count: usize,
     /// Whether drawing parameters have changed
@@ -77,6 +104,94 @@ struct App {
     regenerate: bool,
     /// Data for the currently hovered piece
     piece_data: Option<PieceData>,
+    /// The last piece whose grips were shown
+    last_piece: Option<Piece>,
+    /// Orbits classified for hovering, most recent last
+    orbit_classes: Vec<(Orbit, OrbitClass)>,
+    /// Orbit being classified in the background
+    class_job: Option<std::thread::JoinHandle<(Orbit, OrbitClass)>>,
+    /// Whether clicks add, move and delete circles instead of exploring
+    edit_mode: bool,
+    /// Whether clicks twist circles instead of exploring
+    play_mode: bool,
+    /// Regions found by autofill in play mode, moved by the twists so far
+    state: PuzzleState,
+    /// Whether the state has been twisted and needs drawing from scratch
+    redraw_state: bool,
+    /// Number of random twists to scramble with
+    scramble_count: usize,
+    /// Path of the move log to open or save
+    log_path: String,
+    /// Seconds each twist takes to animate
+    twist_time: f32,
+    /// Twist being animated, and when it started
+    twisting: Option<(Twist, std::time::Instant)>,
+    /// Path of the picture to scramble in play mode
+    texture_path: String,
+    /// Picture laid over the solved state, if one is loaded
+    texture: Option<Arc<render::Image>>,
+    /// The current state rendered with the texture, shown over the canvas
+    picture: Option<egui::TextureHandle>,
+    /// Picture being rendered in the background
+    picture_job: Option<std::thread::JoinHandle<render::Image>>,
+    /// Whether the picture is out of date
+    picture_stale: bool,
+    /// Circle whose centre is being dragged in edit mode
+    dragged_circle: Option<usize>,
+    /// Whether to compute the piece group on the next frame
+    find_group: bool,
+    /// Piece group being computed in the background
+    group_job: Option<std::thread::JoinHandle<Option<PieceGroup>>>,
+    /// Description of the piece group, once computed
+    group_summary: Option<String>,
+    /// The last piece group computed, for solving
+    piece_group: Option<Arc<PieceGroup>>,
+    /// Whether to search for a solution once the piece group is known
+    find_solution: bool,
+    /// Solution being searched for in the background, along with the moves
+    /// it solves
+    solve_job: Option<std::thread::JoinHandle<SolveResult>>,
+    /// Result of the last search for a solution
+    solve_status: Option<String>,
+    /// Generators to find commutators of
+    commutator_gens: [usize; 2],
+    /// Whether to search for commutators once the piece group is known
+    find_commutators: bool,
+    /// Commutators being searched for in the background
+    commutator_job: Option<std::thread::JoinHandle<Vec<Algorithm>>>,
+    /// Commutators found, fewest pieces first
+    commutators: Vec<Algorithm>,
+    /// Index of the commutator whose pieces are highlighted
+    selected_commutator: Option<usize>,
+    /// Result of the last search for commutators
+    commutator_status: Option<String>,
+    /// Path of the configuration file to open or save
+    config_path: String,
+    /// Result of the last open or save
+    config_status: Option<String>,
+    /// Why the last circles couldn't be linked
+    link_status: Option<String>,
+    /// Size in pixels of rendered images
+    render_size: [u32; 2],
+    /// Path to render images to
+    render_path: String,
+    /// Image being rendered in the background, which returns a status message
+    render_job: Option<std::thread::JoinHandle<String>>,
+    /// Path to export SVG diagrams to
+    svg_path: String,
+    /// Result of the last render or export
+    export_status: Option<String>,
+    /// Whether the sweep window is open
+    show_sweep: bool,
+    /// Parameters for the next sweep, with the current puzzle as template
+    sweep: Sweep,
+    /// Parameters of the sweep whose cells are shown
+    swept: Option<Sweep>,
+    /// Cells indexed by distance then radius, filled in as they arrive
+    sweep_cells: Vec<Option<SweepCell>>,
+    /// Cells being computed in the background
+    sweep_job: Option<mpsc::Receiver<(usize, usize, SweepCell)>>,
+    sweep_metric: SweepMetric,
     camera: MobiusTransform,
 }
 impl App {
@@ -85,125 +200,679 @@ impl App {
             gfx: Arc::new(GraphicsState::new(
                 cc.wgpu_render_state.as_ref().expect("No render state"),
             )),
-            circles: vec![],
+            explorer: Explorer::new(PuzzleDefinition::new(vec![], Curvature::EUCLIDEAN), 500),
             scale: 0.5,
-            depth: 500,
             grip_rad: 0.05,
             grip_cuts: false,
             autofill: false,
             index: 0,
             pixel_mask: BitVec::EMPTY.into_boxed_bitslice(),
-            curvature: Curvature::Euclidean,
             circle_distance: 1.,
             circle_count: 2,
             reset: true,
             regenerate: true,
             piece_data: None,
+            last_piece: None,
+            orbit_classes: vec![],
+            class_job: None,
+            edit_mode: false,
+            play_mode: false,
+            state: PuzzleState::default(),
+            redraw_state: false,
+            scramble_count: 20,
+            log_path: "moves.ron".to_string(),
+            twist_time: 0.3,
+            twisting: None,
+            texture_path: "texture.png".to_string(),
+            texture: None,
+            picture: None,
+            picture_job: None,
+            picture_stale: false,
+            dragged_circle: None,
+            find_group: false,
+            group_job: None,
+            group_summary: None,
+            piece_group: None,
+            find_solution: false,
+            solve_job: None,
+            solve_status: None,
+            commutator_gens: [0, 1],
+            find_commutators: false,
+            commutator_job: None,
+            commutators: vec![],
+            selected_commutator: None,
+            commutator_status: None,
+            config_path: "puzzle.ron".to_string(),
+            config_status: None,
+            link_status: None,
+            render_size: [2000, 2000],
+            render_path: "render.png".to_string(),
+            render_job: None,
+            svg_path: "diagram.svg".to_string(),
+            export_status: None,
+            show_sweep: false,
+            sweep: Sweep {
+                template: PuzzleDefinition::new(vec![], Curvature::EUCLIDEAN),
+                distances: 0.0..=2.0,
+                radii: 0.2..=1.0,
+                steps: 16,
+                depth: 500,
+            },
+            swept: None,
+            sweep_cells: vec![],
+            sweep_job: None,
+            sweep_metric: SweepMetric::MaxOrbit,
             camera: MobiusTransform::IDENT,
         }
     }
 
-    fn expand_seed(&self, seed: Pos, circles: &mut Vec<GraphicsCircle>) {
-        let point_max_rad = |point: Pos| {
-            self.circles
+    fn expand_piece(&mut self, seed: Pos) -> Piece {
+        let piece = self.explorer.piece(seed);
+        let orbit = self.explorer.orbit(seed);
+        self.piece_data = Some(PieceData {
+            grip_count: piece.grips().len() as u32,
+            orbit_size: orbit.len() as u32,
+            truncated_at: orbit.truncated.then_some(self.explorer.depth),
+        });
+        self.last_piece = Some(piece.clone());
+        piece
+    }
+
+    /// Handles clicks and drags on the canvas in edit mode. Circle centres
+    /// can be dragged or right-clicked to delete them, and clicking empty
+    /// space adds a circle.
+    fn edit_circles(
+        &mut self,
+        r: &egui::Response,
+        geom_to_egui: impl Fn(Pos) -> Pos2,
+        egui_to_geom: impl Fn(Pos2) -> Pos,
+    ) {
+        const HANDLE_RADIUS: f32 = 10.;
+        let curvature = self.explorer.puzzle.curvature;
+        let Some(mpos) = r.interact_pointer_pos().or(r.hover_pos()) else {
+            return;
+        };
+        let circle_at = |pos: Pos2| {
+            self.explorer
+                .puzzle
+                .circles
                 .iter()
-                .map(|c| (c.circle.cen.dist_in_space(&point, self.curvature) - c.circle.rad).abs())
-                .reduce(f64::min)
-                .expect("Oops, no circles")
+                .position(|c| geom_to_egui(c.circle.cen).distance(pos) < HANDLE_RADIUS)
         };
-        let mut max_rad = point_max_rad(seed);
-        let mut points = vec![(seed, 0)];
-        let mut pointset: hypermath::collections::ApproxHashMap<Pos, ()> =
-            hypermath::collections::approx_hashmap::ApproxHashMap::new();
-        pointset.insert(&seed, ());
-        for i in 0..self.depth as usize {
-            if i >= points.len() {
-                break;
+        let hovered = circle_at(mpos);
+
+        if r.drag_started_by(egui::PointerButton::Primary) {
+            let origin = r.ctx.input(|i| i.pointer.press_origin()).unwrap_or(mpos);
+            self.dragged_circle = circle_at(origin);
+        }
+        if let Some(i) = self.dragged_circle {
+            let cen = egui_to_geom(mpos);
+            if r.dragged_by(egui::PointerButton::Primary) && cen.is_in_space(curvature) {
+                self.explorer.puzzle.circles[i].circle.cen = cen;
+                self.reset = true;
             }
-            for circle in &self.circles {
-                if circle.contains(&points[i].0) {
-                    let new = circle.rotate_point(points[i].0);
-                    if pointset.insert(&new.into(), ()).is_none() {
-                        points.push((new, i));
-                        max_rad = max_rad.min(point_max_rad(new));
-                    }
-                }
+            if r.drag_stopped() {
+                self.dragged_circle = None;
             }
         }
-        for point in &points {
-            let col = if points.len() as u32 > self.depth {
-                [0.5, 0.5, 0.5, 1.]
-            } else {
-                let col = colorous::SINEBOW.eval_rational(
-                    (calculate_hash(&(points.len() + 1))) as u32 as usize,
-                    u32::MAX as usize + 1,
+
+        if r.clicked() && hovered.is_none() {
+            let cen = egui_to_geom(mpos);
+            if cen.is_in_space(curvature) {
+                self.explorer.puzzle.add_circle(cen);
+                self.circle_count = self.explorer.puzzle.circles.len();
+                self.state = PuzzleState::default();
+                self.twisting = None;
+                self.reset = true;
+            }
+        }
+        if r.secondary_clicked() {
+            if let Some(i) = hovered {
+                self.remove_circle(i);
+            }
+        }
+    }
+
+    /// Removes circle `i`, forgetting the moves made so far since those on
+    /// later circles would now turn different ones.
+    fn remove_circle(&mut self, i: usize) {
+        if self.explorer.puzzle.circles.len() > 1 {
+            self.explorer.puzzle.circles.remove(i);
+            self.circle_count = self.explorer.puzzle.circles.len();
+            self.dragged_circle = None;
+            self.state = PuzzleState::default();
+            self.twisting = None;
+            self.reset = true;
+        }
+    }
+
+    fn save_config(&mut self) {
+        let config = Configuration::new(&self.explorer, self.circle_distance, &self.camera);
+        self.config_status = Some(match config.save(&self.config_path) {
+            Ok(()) => format!("Saved {}", self.config_path),
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn load_config(&mut self) {
+        self.config_status = Some(match Configuration::load(&self.config_path) {
+            Ok(config) => {
+                self.apply_config(config);
+                format!("Opened {}", self.config_path)
+            }
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn apply_config(&mut self, config: Configuration) {
+        self.explorer = config.explorer();
+        self.state = PuzzleState::default();
+        self.twisting = None;
+        self.circle_distance = config.circle_distance;
+        self.circle_count = config.circles.len();
+        self.camera = config.camera;
+        self.reset = true;
+    }
+
+    fn save_log(&mut self) {
+        let log = MoveLog {
+            config: Configuration::new(&self.explorer, self.circle_distance, &self.camera),
+            moves: self.state.moves.clone(),
+        };
+        self.config_status = Some(match log.save(&self.log_path) {
+            Ok(()) => format!("Saved {} moves to {}", log.moves.len(), self.log_path),
+            Err(e) => e.to_string(),
+        });
+    }
+
+    /// Opens a move log in its solved state, with its moves ready to redo one
+    /// at a time.
+    fn load_log(&mut self) {
+        self.config_status = Some(match MoveLog::load(&self.log_path) {
+            Ok(log) => {
+                self.apply_config(log.config);
+                self.state.undone = log.moves.into_iter().rev().collect();
+                self.play_mode = true;
+                format!(
+                    "Opened {}, redo to replay {} moves",
+                    self.log_path,
+                    self.state.undone.len()
+                )
+            }
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn load_texture(&mut self) {
+        self.config_status = Some(match render::Image::load_png(&self.texture_path) {
+            Ok(image) => {
+                let status = format!(
+                    "Opened {}, {}×{}",
+                    self.texture_path, image.width, image.height
                 );
-                [
-                    col.r as f32 / 255.,
-                    col.g as f32 / 255.,
-                    col.b as f32 / 255.,
-                    1.,
-                ]
-            };
-            max_rad = max_rad.min(self.camera.apply_to(point.0).dist_to_inf(self.curvature));
-            let (cen, rad) =
-                Circle::new(point.0, max_rad, self.curvature).euclidean_centre_radius(&self.camera);
-            circles.push(GraphicsCircle {
-                centre: cen.into(),
-                radius: rad as f32,
-                col,
-            });
+                self.texture = Some(Arc::new(image));
+                self.picture_stale = true;
+                status
+            }
+            Err(e) => format!("{}: {e}", self.texture_path),
+        });
+    }
+
+    /// Renders the current state with the texture in the background, once
+    /// any twist has finished, and shows it when done. The picture is drawn
+    /// over the canvas, so twists aren't animated on it.
+    fn update_picture(&mut self, ctx: &egui::Context, target_size: [u32; 2]) {
+        if self
+            .picture_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let job = self.picture_job.take().expect("Picture job vanished");
+            let image = job.join().expect("Picture job panicked");
+            let picture = egui::ColorImage::from_rgba_unmultiplied(
+                [image.width as usize, image.height as usize],
+                bytemuck::cast_slice(&image.pixels),
+            );
+            self.picture = Some(ctx.load_texture("picture", picture, Default::default()));
+        }
+        let Some(texture) = self.texture.clone().filter(|_| self.play_mode) else {
+            self.picture = None;
+            return;
+        };
+        if !self.picture_stale || self.picture_job.is_some() || self.twisting.is_some() {
+            return;
         }
+        let explorer = self.explorer.clone();
+        let moves = self.state.moves.clone();
+        let view = render::View {
+            width: target_size[0],
+            height: target_size[1],
+            scale: self.scale as f64,
+            camera: self.camera.clone(),
+        };
+        self.picture_job = Some(std::thread::spawn(move || {
+            render::render_state(&explorer, &moves, &view, Some(&texture))
+        }));
+        self.picture_stale = false;
     }
 
-    fn expand_piece(&mut self, seed: Pos) -> Piece {
-        let mut grips = vec![];
-        let mut piece_grip_set: hypermath::collections::ApproxHashMap<RotCircle, ()> =
-            hypermath::collections::approx_hashmap::ApproxHashMap::new();
+    /// Searches for a solution in the background once the piece group is
+    /// known, and queues it up to redo when found.
+    fn update_solver(&mut self) {
+        if self.solve_job.as_ref().is_some_and(|job| job.is_finished()) {
+            let job = self.solve_job.take().expect("Solve job vanished");
+            let (moves, solution) = job.join().expect("Solve job panicked");
+            self.solve_status = Some(match solution {
+                _ if moves != self.state.moves => "Puzzle was twisted while solving".to_string(),
+                Some(solution) => {
+                    let len = solution.len();
+                    self.state.undone = solution.into_iter().rev().collect();
+                    format!("Found a {len}-move solution, redo to play it")
+                }
+                None => format!("No solution within {SOLVE_NODES} positions"),
+            });
+        }
+        if !self.find_solution || self.group_job.is_some() || self.solve_job.is_some() {
+            return;
+        }
+        let Some(group) = self.piece_group.clone() else {
+            self.find_group = true;
+            return;
+        };
+        let moves = self.state.moves.clone();
+        self.solve_job = Some(std::thread::spawn(move || {
+            let solution = group.solve(&moves, SOLVE_NODES);
+            (moves, solution)
+        }));
+        self.solve_status = Some("Solving...".to_string());
+        self.find_solution = false;
+    }
 
-        let base_grips = GripSet {
-            circles: self.circles.clone(),
+    /// Searches for commutators of the chosen generators in the background
+    /// once the piece group is known.
+    fn update_commutators(&mut self) {
+        let [a, b] = self.commutator_gens;
+        if self
+            .commutator_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let job = self.commutator_job.take().expect("Commutator job vanished");
+            self.commutators = job.join().expect("Commutator job panicked");
+            self.selected_commutator = (!self.commutators.is_empty()).then_some(0);
+            self.commutator_status = Some(match self.commutators.len() {
+                0 => format!("No commutators of {a} and {b} move anything"),
+                n => format!("{n} commutators of {a} and {b}, fewest pieces first"),
+            });
+        }
+        if !self.find_commutators || self.group_job.is_some() || self.commutator_job.is_some() {
+            return;
+        }
+        let Some(group) = self.piece_group.clone() else {
+            self.find_group = true;
+            return;
         };
-        let mut gripsets = vec![base_grips.clone()];
-        let mut gripset_set: hypermath::collections::ApproxHashMap<GripSet, ()> =
-            hypermath::collections::approx_hashmap::ApproxHashMap::new();
-        gripset_set.insert(&base_grips, ());
-        for (i, g) in self
-            .circles
+        self.commutator_job = Some(std::thread::spawn(move || {
+            group.commutators(a, b, COMMUTATOR_COUNT)
+        }));
+        self.commutator_status = Some("Searching...".to_string());
+        self.find_commutators = false;
+    }
+
+    /// The class of the orbit containing `seed` if it has been found, and
+    /// otherwise starts classifying it in the background unless another
+    /// orbit is being classified already.
+    fn orbit_class_at(&mut self, seed: Pos) -> Option<&(Orbit, OrbitClass)> {
+        if self.class_job.as_ref().is_some_and(|job| job.is_finished()) {
+            let job = self.class_job.take().expect("Classify job vanished");
+            self.orbit_classes
+                .push(job.join().expect("Classify job panicked"));
+            if self.orbit_classes.len() > ORBIT_CLASS_CACHE {
+                self.orbit_classes.remove(0);
+            }
+        }
+        let curvature = self.explorer.puzzle.curvature;
+        let found = self
+            .orbit_classes
             .iter()
-            .enumerate()
-            .filter(|(_, c)| c.contains(&seed))
-        {
-            piece_grip_set.insert(&g, ());
-            grips.push(Grip::new(g.circle.cen.clone(), i));
+            .position(|(orbit, _)| orbit.covers(&seed, curvature));
+        if let Some(i) = found {
+            return Some(&self.orbit_classes[i]);
+        }
+        if self.class_job.is_none() {
+            let explorer = self.explorer.clone();
+            self.class_job = Some(std::thread::spawn(move || {
+                explorer.search_and_classify(seed)
+            }));
         }
+        None
+    }
+
+    fn undo(&mut self) {
+        self.finish_twist();
+        self.redraw_state |= self.state.undo(&self.explorer.puzzle).is_some();
+    }
+
+    fn redo(&mut self) {
+        self.finish_twist();
+        self.redraw_state |= self.state.redo(&self.explorer.puzzle).is_some();
+    }
 
-        for i in 0..self.depth as usize {
-            if i >= gripsets.len() {
-                break;
+    /// Renders the solved state, or the current state exactly in play mode.
+    fn start_render(&mut self) {
+        self.finish_twist();
+        let explorer = self.explorer.clone();
+        let moves = match self.play_mode {
+            true => Some(self.state.moves.clone()),
+            false => None,
+        };
+        let texture = self.texture.clone();
+        let view = render::View {
+            width: self.render_size[0],
+            height: self.render_size[1],
+            scale: self.scale as f64,
+            camera: self.camera.clone(),
+        };
+        let path = self.render_path.clone();
+        self.render_job = Some(std::thread::spawn(move || {
+            let image = match moves {
+                Some(moves) => render::render_state(&explorer, &moves, &view, texture.as_deref()),
+                None => render::render(&explorer, &view),
+            };
+            match image.save_png(&path) {
+                Ok(()) => format!("Rendered {path}"),
+                Err(e) => e.to_string(),
             }
-            for j in 0..gripsets[i].circles.len() {
-                if gripsets[i].circles[j].contains(&seed) {
-                    let new_set = gripsets[i].rotate_by(j);
-                    if gripset_set.insert(&new_set, ()).is_none() {
-                        for (i, grip) in new_set.circles.iter().enumerate() {
-                            if grip.contains(&seed) {
-                                if piece_grip_set.insert(&grip, ()).is_none() {
-                                    grips.push(Grip::new(grip.circle.cen, i));
-                                }
-                            }
-                        }
-                        gripsets.push(new_set);
+        }));
+        self.export_status = Some("Rendering...".to_string());
+    }
+
+    fn export_svg(&mut self) {
+        let view = render::View {
+            width: self.render_size[0],
+            height: self.render_size[1],
+            scale: self.scale as f64,
+            camera: self.camera.clone(),
+        };
+        let grips = match &self.last_piece {
+            Some(piece) => piece.grips().as_slice(),
+            None => &[],
+        };
+        let svg = svg::export(
+            &self.explorer,
+            &view,
+            grips,
+            self.grip_rad as f64,
+            self.grip_cuts,
+        );
+        self.export_status = Some(match std::fs::write(&self.svg_path, svg) {
+            Ok(()) => format!("Exported {}", self.svg_path),
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn start_sweep(&mut self) {
+        let mut sweep = self.sweep.clone();
+        sweep.template = self.explorer.puzzle.clone();
+        let (sender, receiver) = mpsc::channel();
+        let job = sweep.clone();
+        std::thread::spawn(move || {
+            for (i, j) in (0..job.steps).cartesian_product(0..job.steps) {
+                // Stop once the receiver is dropped by a newer sweep.
+                if sender.send((i, j, job.cell(i, j))).is_err() {
+                    break;
+                }
+            }
+        });
+        self.sweep_cells = vec![None; sweep.steps * sweep.steps];
+        self.swept = Some(sweep);
+        self.sweep_job = Some(receiver);
+    }
+
+    fn load_sweep_cell(&mut self, sweep: &Sweep, cell: &SweepCell) {
+        self.explorer.puzzle = sweep.puzzle(cell.distance, cell.radius);
+        self.state = PuzzleState::default();
+        self.twisting = None;
+        self.circle_distance = cell.distance;
+        self.circle_count = self.explorer.puzzle.circles.len();
+        self.camera = MobiusTransform::IDENT;
+        self.reset = true;
+    }
+
+    fn sweep_window(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.sweep_job {
+            let steps = self.swept.as_ref().map_or(0, |sweep| sweep.steps);
+            loop {
+                match receiver.try_recv() {
+                    Ok((i, j, cell)) => self.sweep_cells[i * steps + j] = Some(cell),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        self.sweep_job = None;
+                        break;
                     }
                 }
             }
+            ctx.request_repaint();
         }
-        // *grips = points;
-        self.piece_data = Some(PieceData {
-            grip_count: grips.len() as u32,
-            orbit_size: 0,
+
+        let mut open = self.show_sweep;
+        egui::Window::new("Sweep").open(&mut open).show(ctx, |ui| {
+            let max_radius = self.explorer.puzzle.curvature.max_radius();
+            let range_editor =
+                |ui: &mut egui::Ui, label, range: &mut std::ops::RangeInclusive<f64>, max| {
+                    let (mut start, mut end) = range.clone().into_inner();
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        ui.add(
+                            egui::DragValue::new(&mut start)
+                                .speed(0.01)
+                                .clamp_range(0.0..=max),
+                        );
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut end)
+                                .speed(0.01)
+                                .clamp_range(0.0..=max),
+                        );
+                    });
+                    *range = start..=end;
+                };
+            range_editor(ui, "Distance", &mut self.sweep.distances, 5.);
+            range_editor(ui, "Radius", &mut self.sweep.radii, max_radius);
+            ui.horizontal(|ui| {
+                ui.label("Steps");
+                ui.add(egui::DragValue::new(&mut self.sweep.steps).clamp_range(1..=128));
+                ui.label("Depth");
+                ui.add(egui::DragValue::new(&mut self.sweep.depth).clamp_range(1..=100000));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Sweep").clicked() {
+                    self.start_sweep();
+                }
+                ui.selectable_value(&mut self.sweep_metric, SweepMetric::MaxOrbit, "Max orbit");
+                ui.selectable_value(
+                    &mut self.sweep_metric,
+                    SweepMetric::OrbitCount,
+                    "Orbit count",
+                );
+            });
+
+            let Some(sweep) = self.swept.clone() else {
+                return;
+            };
+            let metric = |cell: &SweepCell| match self.sweep_metric {
+                SweepMetric::MaxOrbit => cell.max_orbit,
+                SweepMetric::OrbitCount => cell.orbit_count,
+            };
+            let max = self
+                .sweep_cells
+                .iter()
+                .flatten()
+                .filter(|cell| !cell.truncated)
+                .map(metric)
+                .max()
+                .unwrap_or(1)
+                .max(2);
+            let (r, painter) = ui.allocate_painter(egui::vec2(320., 320.), egui::Sense::click());
+            let size = r.rect.size() / sweep.steps as f32;
+            // Distance increases to the right and radius upwards.
+            let cell_rect = |i: usize, j: usize| {
+                egui::Rect::from_min_size(
+                    r.rect.left_bottom() + egui::vec2(i as f32, -(j as f32 + 1.)) * size,
+                    size,
+                )
+            };
+            for (i, j) in (0..sweep.steps).cartesian_product(0..sweep.steps) {
+                let col = match &self.sweep_cells[i * sweep.steps + j] {
+                    None => egui::Color32::BLACK,
+                    Some(cell) if cell.truncated => egui::Color32::GRAY,
+                    Some(cell) => {
+                        let t = (metric(cell).max(1) as f64).ln() / (max as f64).ln();
+                        let col = colorous::VIRIDIS.eval_continuous(t);
+                        egui::Color32::from_rgb(col.r, col.g, col.b)
+                    }
+                };
+                painter.rect_filled(cell_rect(i, j), 0., col);
+            }
+
+            let hovered = r.hover_pos().and_then(|pos| {
+                let offset = (pos - r.rect.left_bottom()) / size;
+                let (i, j) = (offset.x.floor(), (-offset.y).floor());
+                let in_grid = (0. ..sweep.steps as f32).contains(&i)
+                    && (0. ..sweep.steps as f32).contains(&j);
+                in_grid.then_some((i as usize, j as usize))
+            });
+            if let Some((i, j)) = hovered {
+                painter.rect_stroke(cell_rect(i, j), 0., (2., egui::Color32::WHITE));
+                if let Some(cell) = self.sweep_cells[i * sweep.steps + j].clone() {
+                    let plus = if cell.truncated { "+" } else { "" };
+                    let r = r.on_hover_text(format!(
+                        "Distance {:.3}, radius {:.3}\nMax orbit {}{plus}, {}{plus} orbits",
+                        cell.distance, cell.radius, cell.max_orbit, cell.orbit_count
+                    ));
+                    if r.clicked() {
+                        self.load_sweep_cell(&sweep, &cell);
+                    }
+                }
+            }
+            ui.label(format!(
+                "Distance {:.2}..{:.2} across, radius {:.2}..{:.2} up; grey cells exceeded depth",
+                sweep.distances.start(),
+                sweep.distances.end(),
+                sweep.radii.start(),
+                sweep.radii.end(),
+            ));
+        });
+        self.show_sweep = open;
+    }
+
+    fn twist(&mut self, mv: Move) {
+        self.finish_twist();
+        if self.twist_time <= 0. {
+            self.redraw_state |= self.state.twist(&self.explorer.puzzle, mv);
+        } else if let Some(twist) = self.state.begin_twist(&self.explorer.puzzle, mv) {
+            self.twisting = Some((twist, std::time::Instant::now()));
+        }
+    }
+
+    /// Completes the twist being animated, if there is one.
+    fn finish_twist(&mut self) {
+        if let Some((twist, _)) = self.twisting.take() {
+            self.state.finish_twist(&self.explorer.puzzle, twist);
+            self.redraw_state = true;
+        }
+    }
+
+    /// Advances the twist being animated, returning the circles to draw over
+    /// the texture. The twisted circle is erased and its regions redrawn,
+    /// along with those in the hole of a ring, or everything is redrawn if
+    /// the twisted part of the screen isn't inside a disc.
+    fn animate_twist(&mut self) -> Vec<GraphicsCircle> {
+        let Some((twist, start)) = &self.twisting else {
+            return vec![];
+        };
+        let t = start.elapsed().as_secs_f64() / self.twist_time as f64;
+        if t >= 1. {
+            self.finish_twist();
+            return vec![];
+        }
+        self.state.animate_twist(&self.explorer.puzzle, twist, t);
+
+        let curvature = self.explorer.puzzle.curvature;
+        let circle = &self.explorer.puzzle.circles[twist.mv.generator];
+        // Only a disc on screen can be erased with one stamp
+        let disc = circle.shape(&self.camera).disc();
+        let Some((cen, rad)) = disc.filter(|_| !circle.inverted && circle.linked.is_empty()) else {
+            self.redraw_state = true;
+            return vec![];
+        };
+        let erase = GraphicsCircle {
+            centre: cen.into(),
+            radius: rad as f32,
+            col: [0.; 4],
+            inverted: false,
+        };
+        let hole = circle.hole();
+        let in_hole = self
+            .state
+            .regions
+            .iter()
+            .filter(|region| hole.as_ref().is_some_and(|hole| hole.contains(&region.pos)));
+        std::iter::once(erase)
+            .chain(in_hole.map(|region| region.graphics_circle(&self.camera, curvature)))
+            .chain(
+                twist
+                    .regions()
+                    .map(|i| self.state.regions[i].graphics_circle(&self.camera, curvature)),
+            )
+            .collect()
+    }
+
+    fn scramble(&mut self) {
+        self.finish_twist();
+        self.state.scramble(
+            &self.explorer.puzzle,
+            self.scramble_count,
+            &mut rand::thread_rng(),
+        );
+        self.redraw_state = true;
+    }
+
+    /// Twists on number keys, with shift turning the other way, and undoes
+    /// and redoes with the usual shortcuts.
+    fn twist_keys(&mut self, ctx: &egui::Context) {
+        use egui::Key::*;
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (undo, redo) = ctx.input(|i| {
+            let ctrl = i.modifiers.command;
+            let z = ctrl && i.key_pressed(Z);
+            (
+                z && !i.modifiers.shift,
+                (z && i.modifiers.shift) || (ctrl && i.key_pressed(Y)),
+            )
+        });
+        if undo {
+            self.undo();
+        }
+        if redo {
+            self.redo();
+        }
+        let keys = [Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9];
+        let moves = ctx.input(|i| {
+            keys.iter()
+                .enumerate()
+                .filter(|(_, &key)| i.key_pressed(key))
+                .map(|(generator, _)| Move {
+                    generator,
+                    inverse: i.modifiers.shift,
+                })
+                .collect_vec()
         });
-        Piece::new(grips)
+        for mv in moves {
+            self.twist(mv);
+        }
     }
 
     fn is_pixel_filled(&self, x: usize, y: usize, width: usize) -> bool {
@@ -221,6 +890,7 @@ impl App {
         let &GraphicsCircle {
             centre: [x, y],
             radius: r,
+            inverted,
             ..
         } = circle;
         let Pos2 { x, y } = geom_to_egui(Pos::new(x as f64, y as f64));
@@ -228,21 +898,44 @@ impl App {
         let r = r * dpi;
         let x = x * dpi;
         let y = y * dpi;
-        let circle_top = ((y + r).floor() as usize).min(self.pixel_mask.len() / width - 1);
-        let circle_bottom = ((y - r).ceil() as usize).max(0);
+        let height = self.pixel_mask.len() / width;
+        let (circle_bottom, circle_top) = match inverted {
+            true => (0, height - 1),
+            false => (
+                ((y - r).ceil() as usize).max(0),
+                ((y + r).floor() as usize).min(height - 1),
+            ),
+        };
 
         for row in circle_bottom..=circle_top {
-            let row_height = row.abs_diff(y as usize);
-            let row_width = ((r * r) - (row_height * row_height) as f32).sqrt().floor() as usize;
+            // Centres may be far off screen for half-planes
+            let row_height = row as f32 - y;
+            let row_width = (r * r - row_height * row_height).max(0.).sqrt().floor() as usize;
             let row_centre = x.floor() as isize;
             let row_start = (row_centre - row_width as isize).clamp(0, width as isize) as usize;
             let row_end = (row_centre + row_width as isize).clamp(0, width as isize) as usize;
-            self.pixel_mask[(row_start + row * width)..(row_end + row * width)].fill(true);
+            let row_mask = &mut self.pixel_mask[row * width..(row + 1) * width];
+            match inverted {
+                true => {
+                    row_mask[..row_start].fill(true);
+                    row_mask[row_end..].fill(true);
+                }
+                false => row_mask[row_start..row_end].fill(true),
+            }
         }
     }
 }
 impl eframe::App for App {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        if self
+            .render_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let job = self.render_job.take().expect("Render job vanished");
+            self.export_status = Some(job.join().expect("Render job panicked"));
+        }
+        self.sweep_window(ctx);
         egui::TopBottomPanel::bottom("Sliders").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.vertical(|ui| {
@@ -258,6 +951,138 @@ impl eframe::App for App {
                     }
                     ui.checkbox(&mut self.grip_cuts, "All Cuts");
                     ui.checkbox(&mut self.autofill, "Autofill");
+                    ui.checkbox(&mut self.edit_mode, "Edit circles");
+                    ui.checkbox(&mut self.play_mode, "Play");
+                    if self.play_mode {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.scramble_count)
+                                    .clamp_range(1..=10000),
+                            );
+                            if ui.button("Scramble").clicked() {
+                                self.scramble();
+                            }
+                            if ui.button("Reset").clicked() {
+                                self.twisting = None;
+                                self.state = PuzzleState::default();
+                                self.reset = true;
+                            }
+                            if ui
+                                .add_enabled(
+                                    self.solve_job.is_none(),
+                                    egui::Button::new("Find solution"),
+                                )
+                                .clicked()
+                            {
+                                self.finish_twist();
+                                self.find_solution = true;
+                            }
+                        });
+                        if let Some(status) = &self.solve_status {
+                            ui.label(status);
+                        }
+                        ui.horizontal(|ui| {
+                            let max = self.explorer.puzzle.circles.len().saturating_sub(1);
+                            for generator in &mut self.commutator_gens {
+                                ui.add(egui::DragValue::new(generator).clamp_range(0..=max));
+                            }
+                            if ui
+                                .add_enabled(
+                                    self.commutator_job.is_none(),
+                                    egui::Button::new("Find commutators"),
+                                )
+                                .clicked()
+                            {
+                                self.find_commutators = true;
+                            }
+                        });
+                        if let Some(status) = &self.commutator_status {
+                            ui.label(status);
+                        }
+                        let mut queued = None;
+                        for (i, alg) in self.commutators.iter().enumerate() {
+                            // What the moves do to the first piece they move
+                            let product = self.piece_group.as_ref().and_then(|group| {
+                                let &piece = alg.moved().first()?;
+                                let pos = *group.pieces.get(piece)?;
+                                let product = self.explorer.puzzle.transform(pos, &alg.moves)?;
+                                Some(format!("Moves piece {piece} by {}", product.describe()))
+                            });
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(
+                                    &mut self.selected_commutator,
+                                    Some(i),
+                                    alg.describe(),
+                                )
+                                .on_hover_text(product.unwrap_or_default());
+                                if ui.small_button("Queue").clicked() {
+                                    queued = Some(alg.moves.clone());
+                                }
+                            });
+                        }
+                        if let Some(moves) = queued {
+                            self.finish_twist();
+                            self.state.undone = moves.into_iter().rev().collect();
+                        }
+                        ui.add(
+                            egui::Slider::new(&mut self.twist_time, (0.)..=(2.)).text("Twist time"),
+                        );
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(
+                                    !self.state.moves.is_empty(),
+                                    egui::Button::new("Undo"),
+                                )
+                                .clicked()
+                            {
+                                self.undo();
+                            }
+                            if ui
+                                .add_enabled(
+                                    !self.state.undone.is_empty(),
+                                    egui::Button::new("Redo"),
+                                )
+                                .clicked()
+                            {
+                                self.redo();
+                            }
+                            ui.label(format!(
+                                "{} moves, {} undone",
+                                self.state.moves.len(),
+                                self.state.undone.len()
+                            ));
+                            if let Some(group) = &self.piece_group {
+                                ui.label(match group.is_solved(&self.state.moves) {
+                                    true => "Solved",
+                                    false => "Not solved",
+                                });
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.log_path).desired_width(120.),
+                            );
+                            if ui.button("Open log").clicked() {
+                                self.load_log();
+                            }
+                            if ui.button("Save log").clicked() {
+                                self.save_log();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.texture_path)
+                                    .desired_width(120.),
+                            );
+                            if ui.button("Open picture").clicked() {
+                                self.load_texture();
+                            }
+                            if self.texture.is_some() && ui.button("Clear picture").clicked() {
+                                self.texture = None;
+                            }
+                        });
+                    }
+                    ui.checkbox(&mut self.show_sweep, "Sweep");
                     ui.horizontal(|ui| {
                         if ui.button("Reset").clicked() {
                             self.regenerate = true;
@@ -265,35 +1090,83 @@ impl eframe::App for App {
                         if ui.button("Redraw").clicked() {
                             self.reset = true;
                         }
+                        if ui.button("Group").clicked() {
+                            self.find_group = true;
+                        }
                     });
                     if egui::ComboBox::from_label("Curvature")
-                        .selected_text(match self.curvature {
-                            Curvature::Spherical => "Spherical",
-                            Curvature::Euclidean => "Euclidean",
-                            Curvature::Hyperbolic => "Hyperbolic",
-                        })
+                        .selected_text(self.explorer.puzzle.curvature.name())
                         .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut self.curvature,
-                                Curvature::Euclidean,
-                                "Euclidean",
-                            );
-                            ui.selectable_value(
-                                &mut self.curvature,
-                                Curvature::Spherical,
-                                "Spherical",
-                            );
-                            ui.selectable_value(
-                                &mut self.curvature,
-                                Curvature::Hyperbolic,
-                                "Hyperbolic",
-                            );
+                            for curvature in [
+                                Curvature::EUCLIDEAN,
+                                Curvature::SPHERICAL,
+                                Curvature::HYPERBOLIC,
+                            ] {
+                                ui.selectable_value(
+                                    &mut self.explorer.puzzle.curvature,
+                                    curvature,
+                                    curvature.name(),
+                                );
+                            }
                         })
                         .response
                         .changed()
                     {
                         self.regenerate = true;
                     }
+                    // Morphs the circles into the new space rather than
+                    // starting again from evenly spaced ones
+                    let mut k = self.explorer.puzzle.curvature.k();
+                    if ui
+                        .add(egui::Slider::new(&mut k, -1.0..=1.).text("K"))
+                        .changed()
+                    {
+                        self.explorer.puzzle =
+                            self.explorer.puzzle.with_curvature(Curvature::new(k));
+                        self.camera = MobiusTransform::IDENT;
+                        self.reset = true;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.config_path).desired_width(120.),
+                        );
+                        if ui.button("Open").clicked() {
+                            self.load_config();
+                        }
+                        if ui.button("Save").clicked() {
+                            self.save_config();
+                        }
+                    });
+                    if let Some(status) = &self.config_status {
+                        ui.label(status);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut self.render_size[0]).clamp_range(1..=16384),
+                        );
+                        ui.label("×");
+                        ui.add(
+                            egui::DragValue::new(&mut self.render_size[1]).clamp_range(1..=16384),
+                        );
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.render_path).desired_width(120.),
+                        );
+                        if ui
+                            .add_enabled(self.render_job.is_none(), egui::Button::new("Render"))
+                            .clicked()
+                        {
+                            self.start_render();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.svg_path).desired_width(120.));
+                        if ui.button("Export SVG").clicked() {
+                            self.export_svg();
+                        }
+                    });
+                    if let Some(status) = &self.export_status {
+                        ui.label(status);
+                    }
                 });
                 ui.vertical(|ui| {
                     self.reset |= ui
@@ -304,7 +1177,10 @@ impl eframe::App for App {
                         )
                         .changed();
                     self.reset |= ui
-                        .add(egui::Slider::new(&mut self.depth, 100..=100000).logarithmic(true))
+                        .add(
+                            egui::Slider::new(&mut self.explorer.depth, 100..=100000)
+                                .logarithmic(true),
+                        )
                         .changed();
                     self.reset |= ui
                         .add(egui::Slider::new(&mut self.grip_rad, (0.)..=(0.1)))
@@ -313,27 +1189,160 @@ impl eframe::App for App {
                         .add(egui::Slider::new(&mut self.circle_distance, (0.)..=(5.)))
                         .changed();
                     if let Some(data) = &self.piece_data {
-                        ui.label(format!(
-                            "{} grips, {} orbit size",
-                            data.grip_count, data.orbit_size
-                        ));
+                        ui.label(match data.truncated_at {
+                            Some(depth) => format!(
+                                "{} grips, {}+ orbit size (truncated at depth {depth})",
+                                data.grip_count, data.orbit_size
+                            ),
+                            None => {
+                                format!("{} grips, {} orbit size", data.grip_count, data.orbit_size)
+                            }
+                        });
                     }
                 });
 
-                for circle in &mut self.circles {
+                let curvature = self.explorer.puzzle.curvature;
+                let max_radius = curvature.max_radius();
+                if let Some(status) = &self.link_status {
+                    ui.label(status);
+                }
+                let mut removed = None;
+                let mut linked = None;
+                let mut unlinked = None;
+                for (i, circle) in self.explorer.puzzle.circles.iter_mut().enumerate() {
                     ui.vertical(|ui| {
                         self.reset |= ui
                             .add(
-                                egui::Slider::new(&mut circle.circle.rad, (0.)..=(2.))
+                                egui::Slider::new(&mut circle.circle.rad, (0.)..=max_radius)
                                     .clamp_to_range(false),
                             )
                             .changed();
+                        match &mut circle.custom_angle {
+                            Some(angle) => {
+                                self.reset |= ui
+                                    .add(
+                                        egui::Slider::new(angle, (0.)..=TAU)
+                                            .clamp_to_range(false)
+                                            .text("angle"),
+                                    )
+                                    .changed();
+                            }
+                            None => {
+                                self.reset |= ui
+                                    .add(
+                                        egui::Slider::new(&mut circle.step, 2..=16)
+                                            .clamp_to_range(false),
+                                    )
+                                    .changed();
+                                let step = circle.step.max(1);
+                                self.reset |= ui
+                                    .add(
+                                        egui::Slider::new(&mut circle.turns, 1..=step)
+                                            .text("turns"),
+                                    )
+                                    .changed();
+                            }
+                        }
+                        let max_hole = RotCircle::MAX_HOLE * circle.circle.rad;
+                        circle.hole_rad = circle.hole_rad.min(max_hole);
                         self.reset |= ui
-                            .add(egui::Slider::new(&mut circle.step, 2..=16).clamp_to_range(false))
+                            .add(
+                                egui::Slider::new(&mut circle.hole_rad, (0.)..=max_hole)
+                                    .text("hole"),
+                            )
                             .changed();
-                        self.reset |= ui.checkbox(&mut circle.inverted, "Invert").clicked()
+                        let mut free = circle.custom_angle.is_some();
+                        if ui.checkbox(&mut free, "Free angle").clicked() {
+                            let angle = circle.angle();
+                            circle.custom_angle = free.then_some(angle);
+                            self.reset = true;
+                        }
+                        let mut mirrored = circle.mirror.is_some();
+                        if ui.checkbox(&mut mirrored, "Mirror").clicked() {
+                            circle.mirror = mirrored.then_some(0.);
+                            self.reset = true;
+                        }
+                        if let Some(angle) = &mut circle.mirror {
+                            self.reset |= ui
+                                .add(egui::Slider::new(angle, (0.)..=TAU / 2.).text("mirror"))
+                                .changed();
+                        }
+                        if curvature.is_hyperbolic() {
+                            let kind = circle.circle.kind;
+                            // New horocycles and hypercycles head away from the origin
+                            let cen = circle.circle.cen;
+                            let d = cen.dist(&Pos::ORIGIN);
+                            let ideal = kind.ideal().unwrap_or(if d > 0. {
+                                (1. / d) * cen
+                            } else {
+                                Pos::new(1., 0.)
+                            });
+                            ui.horizontal(|ui| {
+                                for (new, name) in [
+                                    (CircleKind::Point, "Circle"),
+                                    (CircleKind::Horocycle { ideal }, "Horocycle"),
+                                    (CircleKind::Hypercycle { ideal }, "Hypercycle"),
+                                ] {
+                                    let selected = std::mem::discriminant(&kind)
+                                        == std::mem::discriminant(&new);
+                                    if ui.radio(selected, name).clicked() && !selected {
+                                        circle.circle.kind = new;
+                                        self.reset = true;
+                                    }
+                                }
+                            });
+                            if let Some(ideal) = circle.circle.kind.ideal() {
+                                let mut angle = ideal.y.atan2(ideal.x);
+                                if ui
+                                    .add(
+                                        egui::Slider::new(&mut angle, -TAU / 2.0..=TAU / 2.)
+                                            .text("ideal"),
+                                    )
+                                    .changed()
+                                {
+                                    circle.circle.kind = circle
+                                        .circle
+                                        .kind
+                                        .with_ideal(Pos::new(angle.cos(), angle.sin()));
+                                    self.reset = true;
+                                }
+                            }
+                        }
+                        self.reset |= ui.checkbox(&mut circle.inverted, "Invert").clicked();
+                        if self.edit_mode && ui.button("Delete").clicked() {
+                            removed = Some(i);
+                        }
+                        if self.edit_mode && i > 0 && ui.button("Link to previous").clicked() {
+                            linked = Some(i);
+                        }
+                        if self.edit_mode
+                            && !circle.linked.is_empty()
+                            && ui
+                                .button(format!("Unlink {}", circle.linked.len()))
+                                .clicked()
+                        {
+                            unlinked = Some(i);
+                        }
                     });
                 }
+                if let Some(i) = removed {
+                    self.remove_circle(i);
+                }
+                if let Some(i) = linked {
+                    self.link_status = match self.explorer.puzzle.link(i - 1, i) {
+                        true => None,
+                        false => Some(format!("Circles {} and {i} overlap", i - 1)),
+                    };
+                }
+                if let Some(i) = unlinked {
+                    self.explorer.puzzle.unlink(i);
+                }
+                if linked.or(unlinked).is_some() {
+                    self.circle_count = self.explorer.puzzle.circles.len();
+                    self.state = PuzzleState::default();
+                    self.twisting = None;
+                    self.reset = true;
+                }
             });
         });
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -361,77 +1370,44 @@ impl eframe::App for App {
 
             if r.dragged_by(egui::PointerButton::Middle) {
                 if r.drag_delta().length() > 0.1 {
-                    let drag = r.drag_delta() / unit;
-                    let drag = Pos::new(drag.x as f64, -drag.y as f64);
-                    let transform_delta = match self.curvature {
-                        Curvature::Spherical => {
-                            if let Some(mpos) = r.interact_pointer_pos() {
-                                let root_pos = egui_to_screen(mpos - r.drag_delta());
-                                let end_pos = egui_to_screen(mpos);
-
-                                let to_origin = MobiusTransform::new([
-                                    [Pos::new(1., 0.), -root_pos],
-                                    [root_pos.conjugate(), Pos::new(1., 0.)],
-                                ]);
-                                let transformed_end_pos = to_origin.apply_to(end_pos);
-                                let inner_transform = MobiusTransform::new([
-                                    [Pos::new(1., 0.), transformed_end_pos],
-                                    [-transformed_end_pos.conjugate(), Pos::new(1., 0.)],
-                                ]);
-
-                                to_origin.inverse() * inner_transform * to_origin
-                                // MobiusTransform::new([
-                                //     [Pos::new(1., 0.), drag],
-                                //     [-drag.conjugate(), Pos::new(1., 0.)],
-                                // ])
-                            } else {
-                                MobiusTransform::IDENT
-                            }
-                        }
-                        Curvature::Euclidean => MobiusTransform::new([
-                            [Pos::new(1., 0.), drag],
-                            [Pos::new(0., 0.), Pos::new(1., 0.)],
-                        ]),
-                        Curvature::Hyperbolic => {
-                            if let Some(mpos) = r.interact_pointer_pos() {
-                                let root_pos = egui_to_screen(mpos - r.drag_delta());
-                                let end_pos = egui_to_screen(mpos);
-
-                                let to_origin = MobiusTransform::new([
-                                    [Pos::new(1., 0.), -root_pos],
-                                    [-root_pos.conjugate(), Pos::new(1., 0.)],
-                                ]);
-                                let transformed_end_pos = to_origin.apply_to(end_pos);
-                                let inner_transform = MobiusTransform::new([
-                                    [Pos::new(1., 0.), transformed_end_pos],
-                                    [transformed_end_pos.conjugate(), Pos::new(1., 0.)],
-                                ]);
-
-                                to_origin.inverse() * inner_transform * to_origin
-
-                            // MobiusTransform::new([
-                            // [Pos::new(1., 0.), drag],
-                            // [drag.conjugate(), Pos::new(1., 0.)],
-                            // ])
-                            } else {
-                                MobiusTransform::IDENT
-                            }
-                        }
+                    let transform_delta = match r.interact_pointer_pos() {
+                        Some(mpos) => MobiusTransform::translation(
+                            egui_to_screen(mpos - r.drag_delta()),
+                            egui_to_screen(mpos),
+                            self.explorer.puzzle.curvature,
+                        ),
+                        None => MobiusTransform::IDENT,
                     };
                     self.camera = transform_delta * self.camera.clone();
-                    self.camera.normalise(self.curvature);
+                    self.camera.normalise(self.explorer.puzzle.curvature);
                     self.reset = true;
                 }
             }
 
             if self.regenerate {
-                self.circles = gen_circles(self.circle_count, self.circle_distance, self.curvature);
+                self.explorer.puzzle = PuzzleDefinition::regular(
+                    self.circle_count,
+                    self.circle_distance,
+                    self.explorer.puzzle.curvature,
+                );
                 self.camera = MobiusTransform::IDENT;
+                self.state = PuzzleState::default();
+                self.twisting = None;
                 self.reset = true;
             }
             if self.reset {
                 self.index = 0;
                 self.pixel_mask = bitbox![0; (target_size[0]*target_size[1]) as usize];
+                self.group_job = None;
+                self.group_summary = None;
+                self.piece_group = None;
+                self.commutators.clear();
+                self.selected_commutator = None;
+                self.orbit_classes.clear();
+                self.class_job = None;
+                self.last_piece = None;
+                self.finish_twist();
+                self.state.regions.clear();
             }
 
             let camera = self.camera.clone();
@@ -439,9 +1415,52 @@ impl eframe::App for App {
             let geom_to_egui = |pos: Pos| screen_to_egui(camera.apply_to(pos));
             let egui_to_geom = |pos: Pos2| camera.inverse().apply_to(egui_to_screen(pos));
 
+            if self.find_group {
+                const GRID: usize = 64;
+                let explorer = self.explorer.clone();
+                self.group_job = Some(std::thread::spawn(move || {
+                    explorer.piece_group(explorer.seeds(GRID))
+                }));
+                self.group_summary = Some("Computing group...".to_string());
+                self.find_group = false;
+            }
+            if self.group_job.as_ref().is_some_and(|job| job.is_finished()) {
+                let job = self.group_job.take().expect("Group job vanished");
+                let group = job.join().expect("Group job panicked");
+                self.group_summary = Some(match &group {
+                    Some(group) => group_summary(group),
+                    None => format!("Orbits truncated at depth {}", self.explorer.depth),
+                });
+                if group.is_none() && self.find_solution {
+                    self.find_solution = false;
+                    self.solve_status = self.group_summary.clone();
+                }
+                if group.is_none() && self.find_commutators {
+                    self.find_commutators = false;
+                    self.commutator_status = self.group_summary.clone();
+                }
+                self.piece_group = group.map(Arc::new);
+            }
+            self.update_solver();
+            self.update_commutators();
+
             let mut circles = vec![];
             let mut grips = vec![];
-            if r.is_pointer_button_down_on() {
+            if self.edit_mode {
+                self.edit_circles(&r, geom_to_egui, egui_to_geom);
+            } else if self.play_mode {
+                self.twist_keys(ctx);
+                let clicked = match (r.clicked(), r.secondary_clicked()) {
+                    (true, _) => Some(false),
+                    (_, true) => Some(true),
+                    _ => None,
+                };
+                if let (Some(inverse), Some(mpos)) = (clicked, r.interact_pointer_pos()) {
+                    if let Some(generator) = self.explorer.generator_at(egui_to_geom(mpos)) {
+                        self.twist(Move { generator, inverse });
+                    }
+                }
+            } else if r.is_pointer_button_down_on() {
                 if let Some(mpos) = ctx.pointer_latest_pos() {
                     //let mpos = itrans(mpos);
                     let seed = egui_to_geom(mpos);
@@ -449,7 +1468,7 @@ impl eframe::App for App {
 
                     // Fill regions
                     if ui.input(|i| i.pointer.primary_down()) {
-                        self.expand_seed(seed, &mut circles);
+                        self.explorer.expand_seed(seed, &self.camera, &mut circles);
                     }
 
                     // Calculate grips
@@ -459,7 +1478,10 @@ impl eframe::App for App {
                 }
             }
 
-            if self.autofill {
+            // Regions in their solved positions, which is what the pixel mask
+            // tracks in play mode
+            let mut solved = vec![];
+            if self.autofill || self.play_mode {
                 if self.pixel_mask.len() != (target_size[0] * target_size[1]) as usize {
                     self.pixel_mask = bitbox![0; (target_size[0]*target_size[1]) as usize];
                 }
@@ -475,7 +1497,23 @@ impl eframe::App for App {
                             (self.index % target_size[0] as usize) as f32,
                             (self.index / target_size[0] as usize) as f32,
                         ));
-                        self.expand_seed(seed, &mut circles);
+                        if self.play_mode {
+                            let curvature = self.explorer.puzzle.curvature;
+                            let regions = self.explorer.orbit_regions(seed);
+                            solved.extend(
+                                regions
+                                    .iter()
+                                    .map(|region| region.graphics_circle(&self.camera, curvature)),
+                            );
+                            let moved = self.state.add_regions(&self.explorer.puzzle, regions);
+                            circles.extend(
+                                moved
+                                    .iter()
+                                    .map(|region| region.graphics_circle(&self.camera, curvature)),
+                            );
+                        } else {
+                            self.explorer.expand_seed(seed, &self.camera, &mut circles);
+                        }
                     }
                     self.index =
                         (self.index + 1000000007) % (target_size[0] * target_size[1]) as usize;
@@ -483,20 +1521,41 @@ impl eframe::App for App {
                 }
             }
 
-            for circle in &circles {
+            let filled = if self.play_mode { &solved } else { &circles };
+            for circle in filled {
                 let dpi = ctx.pixels_per_point();
                 self.fill_pixel_circle(circle, target_size[0] as usize, dpi, screen_to_egui, unit);
             }
 
+            circles.extend(self.animate_twist());
+            let clear = self.reset || self.redraw_state;
+            self.picture_stale |= clear;
+            self.update_picture(ctx, target_size);
+            if self.redraw_state {
+                let curvature = self.explorer.puzzle.curvature;
+                circles = self
+                    .state
+                    .regions
+                    .iter()
+                    .map(|region| region.graphics_circle(&self.camera, curvature))
+                    .collect();
+                self.redraw_state = false;
+            }
             let out_circles = if circles.len() > 0 {
-                circles.iter().map(|c| c.get_instance(scale)).collect()
+                circles
+                    .iter()
+                    .map(|c| CircleInstance::from_graphics_circle(c, scale))
+                    .collect()
             } else {
-                vec![GraphicsCircle {
-                    centre: [f32::NAN; 2],
-                    radius: f32::NAN,
-                    col: [f32::NAN; 4],
-                }
-                .get_instance(scale)]
+                vec![CircleInstance::from_graphics_circle(
+                    &GraphicsCircle {
+                        centre: [f32::NAN; 2],
+                        radius: f32::NAN,
+                        col: [f32::NAN; 4],
+                        inverted: false,
+                    },
+                    scale,
+                )]
             };
             let painter = ui.painter_at(egui_rect);
             painter.add(eframe::egui_wgpu::Callback::new_paint_callback(
@@ -509,31 +1568,113 @@ impl eframe::App for App {
                         height: target_size[1],
                         depth_or_array_layers: 1,
                     },
-                    clear: self.reset,
+                    clear,
                 },
             ));
-            if self.curvature == Curvature::Hyperbolic {
-                painter.circle_stroke(cen, unit, (1., egui::Color32::LIGHT_GRAY));
+            if let Some(picture) = &self.picture {
+                painter.image(
+                    picture.id(),
+                    egui_rect,
+                    egui::Rect::from_min_max(pos2(0., 0.), pos2(1., 1.)),
+                    egui::Color32::WHITE,
+                );
             }
-            for (i, circle) in self.circles.iter().enumerate() {
-                let (cen, rad) = circle.euclidean_centre_radius(&self.camera);
-                painter.circle_stroke(screen_to_egui(cen), rad as f32 * unit, (4., gen_colors(i)));
+            let curvature = self.explorer.puzzle.curvature;
+            if curvature.is_hyperbolic() {
+                let bound = unit * curvature.model_bound() as f32;
+                painter.circle_stroke(cen, bound, (1., egui::Color32::LIGHT_GRAY));
             }
-            for Grip { pos, id } in grips {
-                let circle = Circle::new(pos, self.grip_rad as f64, self.curvature);
-                let (cen, rad) = circle.euclidean_centre_radius(&self.camera);
-                let cen = screen_to_egui(cen);
-                painter.circle(
-                    cen,
-                    rad as f32 * unit,
-                    gen_colors(id),
-                    (2., egui::Color32::LIGHT_GRAY),
-                );
-                if self.grip_cuts {
-                    let circle = Circle::new(pos, self.circles[id].circle.rad, self.curvature);
-                    let (cen, rad) = circle.euclidean_centre_radius(&self.camera);
-                    let cen = screen_to_egui(cen);
-                    painter.circle_stroke(cen, rad as f32 * unit, (2., egui::Color32::LIGHT_GRAY));
+            for (i, circle) in self.explorer.puzzle.circles.iter().enumerate() {
+                for boundary in circle.boundaries() {
+                    let shape = boundary.shape(&self.camera);
+                    stroke_shape(&painter, shape, screen_to_egui, unit, (4., gen_colors(i)));
+                }
+                if self.edit_mode {
+                    painter.circle(
+                        geom_to_egui(circle.circle.cen),
+                        6.,
+                        gen_colors(i),
+                        (2., egui::Color32::LIGHT_GRAY),
+                    );
+                }
+            }
+            if let (Some(group), Some(alg)) = (
+                &self.piece_group,
+                self.selected_commutator
+                    .and_then(|i| self.commutators.get(i)),
+            ) {
+                for piece in alg.moved() {
+                    let from = geom_to_egui(group.pieces[piece]);
+                    let to = geom_to_egui(group.pieces[alg.permutation.apply(piece)]);
+                    if !(from.is_finite() && to.is_finite()) {
+                        continue;
+                    }
+                    painter.circle_stroke(from, 6., (2., egui::Color32::WHITE));
+                    painter.arrow(from, to - from, (2., egui::Color32::WHITE));
+                }
+            }
+            for grip @ Grip { pos, id, .. } in grips {
+                let circle = Circle::new(pos, self.grip_rad as f64, self.explorer.puzzle.curvature);
+                if let Some((cen, rad)) = circle.shape(&self.camera).disc() {
+                    painter.circle(
+                        screen_to_egui(cen),
+                        rad as f32 * unit,
+                        gen_colors(id),
+                        (2., egui::Color32::LIGHT_GRAY),
+                    );
+                }
+                let cut = self
+                    .grip_cuts
+                    .then(|| grip.cut(&self.explorer.puzzle))
+                    .flatten();
+                if let Some(cut) = cut {
+                    for boundary in cut.boundaries() {
+                        let shape = boundary.shape(&self.camera);
+                        stroke_shape(
+                            &painter,
+                            shape,
+                            screen_to_egui,
+                            unit,
+                            (2., egui::Color32::LIGHT_GRAY),
+                        );
+                    }
+                }
+            }
+            if let Some(mpos) = r.hover_pos().filter(|_| !self.edit_mode && !r.dragged()) {
+                let seed = egui_to_geom(mpos);
+                if seed.is_in_space(self.explorer.puzzle.curvature) {
+                    let puzzle = self.explorer.puzzle.clone();
+                    let mut text = match self.orbit_class_at(seed) {
+                        Some((orbit, class)) => {
+                            // The product of the moves the search took to
+                            // its last point, from the seed of that orbit
+                            let word = orbit.word(orbit.len() - 1);
+                            let product = puzzle.transform(orbit.points[0].0, &word);
+                            match product.filter(|_| !word.is_empty()) {
+                                Some(product) => format!(
+                                    "{class}\nLast point found by {}: {}",
+                                    word.iter().join(" "),
+                                    product.describe()
+                                ),
+                                None => class.to_string(),
+                            }
+                        }
+                        None => "Classifying orbit...".to_string(),
+                    };
+                    if self.play_mode && !self.state.moves.is_empty() {
+                        // Following the point back to where it started
+                        let undo = self
+                            .state
+                            .moves
+                            .iter()
+                            .rev()
+                            .map(|mv| mv.reversed())
+                            .collect_vec();
+                        if let Some(product) = puzzle.transform(seed, &undo) {
+                            text += &format!("\nMoved here by {}", product.inverse().describe());
+                        }
+                    }
+                    r.clone().on_hover_text(text);
                 }
             }
             // pixel mask debug visual
@@ -555,39 +1696,27 @@ impl eframe::App for App {
     }
 }
 
-fn calculate_hash<T: std::hash::Hash>(t: &T) -> u64 {
-    let mut s = std::hash::DefaultHasher::new();
-    t.hash(&mut s);
-    std::hash::Hasher::finish(&s)
-}
-
-#[derive(Debug, Clone)]
-struct GripSet {
-    circles: Vec<RotCircle>,
-}
-impl GripSet {
-    fn rotate_by(&self, index: usize) -> Self {
-        let circles = self
-            .circles
-            .iter()
-            .map(|circle| self.circles[index].rotate_circle(circle))
-            .collect_vec();
-        Self { circles }
-    }
-}
-impl hypermath::collections::approx_hashmap::ApproxHashMapKey for GripSet {
-    type Hash = Vec<<Pos as hypermath::collections::approx_hashmap::ApproxHashMapKey>::Hash>;
-
-    fn approx_hash(
-        &self,
-        mut float_hash_fn: impl FnMut(
-            hypermath::prelude::Float,
-        ) -> hypermath::collections::approx_hashmap::FloatHash,
-    ) -> Self::Hash {
-        self.circles
-            .iter()
-            .map(|circle| circle.circle.cen.approx_hash(&mut float_hash_fn))
-            .collect()
+/// Outlines the edge of `shape` in screen space, which may be a circle or a
+/// line.
+fn stroke_shape(
+    painter: &egui::Painter,
+    shape: Shape,
+    screen_to_egui: impl Fn(Pos) -> Pos2,
+    unit: f32,
+    stroke: impl Into<egui::Stroke>,
+) {
+    match shape {
+        Shape::Disc { cen, rad } | Shape::Exterior { cen, rad } => {
+            painter.circle_stroke(screen_to_egui(cen), rad as f32 * unit, stroke);
+        }
+        Shape::HalfPlane { point, normal } => {
+            // Long enough to cross the view at any zoom worth using
+            let along = 1e4 * Pos::new(-normal.y, normal.x);
+            painter.line_segment(
+                [screen_to_egui(point - along), screen_to_egui(point + along)],
+                stroke,
+            );
+        }
     }
 }
 
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use crate::geom::Pos;
+
+use super::{Explorer, Orbit};
+
+/// Whether an orbit is finite, judged from how its search progressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrbitClass {
+    /// The orbit closed with this many points
+    Finite(usize),
+    /// The search frontier kept growing while the regions kept shrinking, or
+    /// grew fast enough to be exponential
+    LikelyInfinite {
+        /// Ratio of frontier sizes over the last doubling of the search
+        growth: f64,
+        /// Ratio of `max_rad` over the last doubling of the search
+        shrinkage: f64,
+    },
+    /// The search was cut off without clear evidence either way
+    Undecided { found: usize },
+}
+impl fmt::Display for OrbitClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrbitClass::Finite(len) => write!(f, "Finite, {len} points"),
+            OrbitClass::LikelyInfinite { growth, shrinkage } => write!(
+                f,
+                "Likely infinite (frontier ×{growth:.2}, radius ×{shrinkage:.2} per doubling)"
+            ),
+            OrbitClass::Undecided { found } => write!(f, "Undecided after {found} points"),
+        }
+    }
+}
+
+/// Frontier growth per doubling above which growth is taken to be
+/// exponential on its own.
+const EXPONENTIAL_GROWTH: f64 = 1.5;
+
+impl Explorer {
+    /// Classifies the orbit of `seed` by searching it up to `depth` and
+    /// comparing the search at successive doublings of the number of points
+    /// expanded. The orbit is likely infinite if, over the last two
+    /// doublings, the frontier of unexpanded points never shrank and either
+    /// `max_rad` shrank each time or the frontier grew exponentially.
+    pub fn classify_orbit(&self, seed: Pos) -> OrbitClass {
+        self.search_and_classify(seed).1
+    }
+
+    /// The orbit of `seed` along with its classification, as
+    /// [`Self::classify_orbit`], so that other points can be checked against
+    /// the orbit.
+    pub fn search_and_classify(&self, seed: Pos) -> (Orbit, OrbitClass) {
+        let mut checkpoints = vec![];
+        let orbit = self.search_orbit(seed, |expanded, found, max_rad| {
+            checkpoints.push((found - expanded, max_rad));
+        });
+        let class = classify(&orbit, &checkpoints);
+        (orbit, class)
+    }
+}
+
+/// Classifies `orbit` from the frontier size and `max_rad` at each
+/// checkpoint of its search.
+fn classify(orbit: &Orbit, checkpoints: &[(usize, f64)]) -> OrbitClass {
+    if !orbit.truncated {
+        return OrbitClass::Finite(orbit.len());
+    }
+    let undecided = OrbitClass::Undecided { found: orbit.len() };
+    let [.., (f0, r0), (f1, r1), (f2, r2)] = checkpoints[..] else {
+        return undecided;
+    };
+    if f0 == 0 || f1 == 0 {
+        return undecided;
+    }
+    let growths = [f1 as f64 / f0 as f64, f2 as f64 / f1 as f64];
+    let shrinkages = [r1 / r0, r2 / r1];
+    let growing = growths.iter().all(|&g| g >= 1.);
+    let shrinking = shrinkages.iter().all(|&s| s < 1.);
+    let exponential = growths.iter().all(|&g| g >= EXPONENTIAL_GROWTH);
+    if growing && (shrinking || exponential) {
+        OrbitClass::LikelyInfinite {
+            growth: growths[1],
+            shrinkage: shrinkages[1],
+        }
+    } else {
+        undecided
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geom::{Curvature, Pos};
+    use crate::puzzle::{Explorer, Orbit, PuzzleDefinition};
+
+    use super::{classify, OrbitClass};
+
+    fn orbit(len: usize, truncated: bool) -> Orbit {
+        Orbit {
+            points: vec![(Pos::ORIGIN, 0); len],
+            moves: vec![None; len],
+            max_rad: 0.1,
+            truncated,
+        }
+    }
+
+    #[test]
+    fn closed_orbits_are_finite() {
+        let checkpoints = [(1, 0.1), (2, 0.1), (1, 0.1)];
+        assert_eq!(
+            classify(&orbit(12, false), &checkpoints),
+            OrbitClass::Finite(12)
+        );
+    }
+
+    #[test]
+    fn growing_orbits_are_likely_infinite() {
+        let shrinking = [(3, 0.4), (4, 0.2), (6, 0.1), (12, 0.05)];
+        assert_eq!(
+            classify(&orbit(100, true), &shrinking),
+            OrbitClass::LikelyInfinite {
+                growth: 2.,
+                shrinkage: 0.5
+            }
+        );
+        let exponential = [(4, 0.1), (8, 0.1), (16, 0.1)];
+        assert!(matches!(
+            classify(&orbit(100, true), &exponential),
+            OrbitClass::LikelyInfinite { .. }
+        ));
+    }
+
+    #[test]
+    fn cut_off_orbits_are_undecided() {
+        let undecided = OrbitClass::Undecided { found: 100 };
+        let cases: [&[(usize, f64)]; 4] = [
+            // Too few checkpoints to judge
+            &[(4, 0.1), (8, 0.05)],
+            // Growing slowly without shrinking
+            &[(4, 0.1), (5, 0.1), (6, 0.1)],
+            // Shrinking frontier
+            &[(8, 0.2), (6, 0.1), (5, 0.05)],
+            &[(0, 0.2), (6, 0.1), (12, 0.05)],
+        ];
+        for checkpoints in cases {
+            assert_eq!(classify(&orbit(100, true), checkpoints), undecided);
+        }
+    }
+
+    fn ring(distance: f64, step: u32, rad: f64) -> Explorer {
+        let mut puzzle = PuzzleDefinition::regular(3, distance, Curvature::EUCLIDEAN);
+        for circle in &mut puzzle.circles {
+            circle.step = step;
+            circle.circle.rad = rad;
+        }
+        Explorer::new(puzzle, 3000)
+    }
+
+    #[test]
+    fn finite_puzzle_closes() {
+        let class = ring(1., 2, 0.6).classify_orbit(Pos::new(0.013, 0.021));
+        assert!(matches!(class, OrbitClass::Finite(_)), "{class}");
+    }
+
+    #[test]
+    fn jumbling_puzzle_keeps_growing() {
+        let class = ring(0.5, 5, 0.5).classify_orbit(Pos::new(0.013, 0.021));
+        assert!(
+            matches!(class, OrbitClass::LikelyInfinite { .. }),
+            "{class}"
+        );
+    }
+}
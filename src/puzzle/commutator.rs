@@ -0,0 +1,205 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use itertools::Itertools;
+
+use crate::group::Permutation;
+
+use super::{Move, PieceGroup};
+
+/// A short sequence of moves, with its effect on the pieces.
+#[derive(Debug, Clone)]
+pub struct Algorithm {
+    pub moves: Vec<Move>,
+    /// Where the piece in each position ends up
+    pub permutation: Permutation,
+}
+impl Algorithm {
+    /// Positions of the pieces moved.
+    pub fn moved(&self) -> Vec<usize> {
+        self.permutation.support().collect()
+    }
+
+    /// The moves written out as in a move log, followed by how many cycles
+    /// of each length the pieces move in.
+    pub fn describe(&self) -> String {
+        let cycles = self
+            .permutation
+            .cycles()
+            .iter()
+            .map(|c| c.len())
+            .counts()
+            .into_iter()
+            .sorted_by(|a, b| b.cmp(a))
+            .map(|(len, count)| format!("{count} × {len}-cycle"))
+            .join(", ");
+        format!(
+            "{}: {} pieces, {cycles}",
+            self.moves.iter().join(" "),
+            self.permutation.support().count(),
+        )
+    }
+}
+
+/// A commutator found so far, ordered so that the worst is greatest.
+struct Candidate {
+    /// Pieces moved, moves, then the order found in
+    key: (usize, usize, usize),
+    /// Indices of `X` and `Y` in the list of words
+    words: (usize, usize),
+    permutation: Permutation,
+}
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+impl Eq for Candidate {}
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// The moves undoing `moves`.
+fn invert(moves: &[Move]) -> Vec<Move> {
+    moves.iter().rev().map(|mv| mv.reversed()).collect()
+}
+
+impl PieceGroup {
+    /// Each nontrivial power of `generator`, turning whichever way is
+    /// shorter.
+    fn powers(&self, generator: usize) -> Vec<Vec<Move>> {
+        let order = self.generators[generator].order();
+        (1..order)
+            .map(|k| {
+                let (count, inverse) = match k <= order / 2 {
+                    true => (k, false),
+                    false => (order - k, true),
+                };
+                vec![Move { generator, inverse }; count]
+            })
+            .collect()
+    }
+
+    /// Searches commutators `X Y X' Y'` where `X` and `Y` are powers of
+    /// generators `a` and `b`, products of a power of each, or a power of
+    /// one conjugated by a power of the other. Returns the `count` which move
+    /// the fewest pieces, with different effects, shortest first among
+    /// equals.
+    pub fn commutators(&self, a: usize, b: usize, count: usize) -> Vec<Algorithm> {
+        if a == b || a >= self.generators.len() || b >= self.generators.len() {
+            return vec![];
+        }
+        let (powers_a, powers_b) = (self.powers(a), self.powers(b));
+        let mut words = powers_a.iter().chain(&powers_b).cloned().collect_vec();
+        for (x, y) in powers_a
+            .iter()
+            .cartesian_product(&powers_b)
+            .flat_map(|(x, y)| [(x, y), (y, x)])
+        {
+            words.push([&x[..], y].concat());
+            words.push([&x[..], y, &invert(x)].concat());
+        }
+
+        // The effect of each word and its inverse, so each commutator is
+        // just four compositions
+        let perms = words
+            .iter()
+            .map(|word| {
+                self.permutation(word)
+                    .expect("Generators were checked above")
+            })
+            .collect_vec();
+        let inverses = perms.iter().map(Permutation::inverse).collect_vec();
+
+        // The best `count` so far, worst on top. Equal effects are only kept
+        // once, by the shortest
+        let mut best = BinaryHeap::<Candidate>::with_capacity(count + 1);
+        let mut permutation = Permutation::identity(self.pieces.len());
+        for (found, (i, j)) in (0..words.len())
+            .cartesian_product(0..words.len())
+            .enumerate()
+        {
+            permutation.clone_from(&perms[i]);
+            permutation.then_assign(&perms[j]);
+            permutation.then_assign(&inverses[i]);
+            permutation.then_assign(&inverses[j]);
+            if permutation.is_identity() {
+                continue;
+            }
+            let len = 2 * (words[i].len() + words[j].len());
+            let key = (permutation.support().count(), len, found);
+            if best.len() == count && best.peek().is_some_and(|worst| worst.key <= key) {
+                continue;
+            }
+            if let Some(same) = best.iter().find(|c| c.permutation == permutation) {
+                if (same.key.0, same.key.1) <= (key.0, key.1) {
+                    continue;
+                }
+                best.retain(|c| c.permutation != permutation);
+            }
+            best.push(Candidate {
+                key,
+                words: (i, j),
+                permutation: permutation.clone(),
+            });
+            if best.len() > count {
+                best.pop();
+            }
+        }
+        best.into_sorted_vec()
+            .into_iter()
+            .map(
+                |Candidate {
+                     words: (i, j),
+                     permutation,
+                     ..
+                 }| {
+                    let (x, y) = (&words[i], &words[j]);
+                    let moves = [&x[..], y, &invert(x), &invert(y)].concat();
+                    Algorithm { moves, permutation }
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::puzzle::group::tests::regular_group;
+
+    #[test]
+    fn fewest_pieces_first() {
+        for (count, step, rad) in [(3, 2, 0.6), (2, 3, 0.55)] {
+            let group = regular_group(count, step, rad);
+            let found = group.commutators(0, 1, 8);
+            assert!(!found.is_empty() && found.len() <= 8);
+            for alg in &found {
+                assert_eq!(group.permutation(&alg.moves), Some(alg.permutation.clone()));
+                assert!(!alg.permutation.is_identity());
+            }
+            let keys = found
+                .iter()
+                .map(|alg| (alg.permutation.support().count(), alg.moves.len()))
+                .collect_vec();
+            assert!(keys.windows(2).all(|w| w[0] <= w[1]));
+            assert!(found.iter().map(|alg| &alg.permutation).all_unique());
+
+            // Asking for more keeps the best ones the same
+            let more = group.commutators(0, 1, 20);
+            let more_keys = more
+                .iter()
+                .map(|alg| (alg.permutation.support().count(), alg.moves.len()))
+                .collect_vec();
+            assert_eq!(keys, more_keys[..keys.len()]);
+        }
+    }
+}
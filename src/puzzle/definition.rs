@@ -0,0 +1,319 @@
+use itertools::Itertools;
+
+use crate::geom::{Curvature, MobiusTransform, Pos, RotCircle, Turn};
+
+use super::{explorer::hashed_colour, Move};
+
+/// Farthest a centre is put from the origin by
+/// [`PuzzleDefinition::with_curvature`], as a fraction of the distance to the
+/// antipode.
+const FARTHEST_CENTRE: f64 = 0.999;
+
+/// A set of transformation circles in a fixed geometry.
+#[derive(Debug, Clone)]
+pub struct PuzzleDefinition {
+    /// Transformation circles
+    pub circles: Vec<RotCircle>,
+    pub curvature: Curvature,
+}
+impl PuzzleDefinition {
+    pub fn new(circles: Vec<RotCircle>, curvature: Curvature) -> Self {
+        Self { circles, curvature }
+    }
+
+    /// `count` circles spaced evenly around the origin, see [`gen_circles`].
+    pub fn regular(count: usize, distance: f64, curvature: Curvature) -> Self {
+        Self::new(gen_circles(count, distance, curvature), curvature)
+    }
+
+    /// Colour of `point` in the solved state. This depends on which circles
+    /// contain the point, unlike orbit colours, so twists visibly move it.
+    pub fn solved_colour(&self, point: Pos) -> [f32; 4] {
+        let inside = self
+            .circles
+            .iter()
+            .positions(|c| c.contains(&point))
+            .collect_vec();
+        hashed_colour(&inside)
+    }
+
+    /// Corners of the smallest box containing every circle, ignoring any
+    /// which reach infinity, and no bigger than the disk modelling
+    /// hyperbolic space, since horocycles and hypercycles reach its edge.
+    pub fn bounds(&self) -> (Pos, Pos) {
+        let (min, max) = self
+            .circles
+            .iter()
+            .filter_map(|c| c.shape(&MobiusTransform::IDENT).disc())
+            .fold(
+                (
+                    Pos::new(f64::INFINITY, f64::INFINITY),
+                    -Pos::new(f64::INFINITY, f64::INFINITY),
+                ),
+                |(min, max), (cen, rad)| {
+                    (
+                        Pos::new(min.x.min(cen.x - rad), min.y.min(cen.y - rad)),
+                        Pos::new(max.x.max(cen.x + rad), max.y.max(cen.y + rad)),
+                    )
+                },
+            );
+        let bound = self.curvature.model_bound();
+        (
+            Pos::new(min.x.max(-bound), min.y.max(-bound)),
+            Pos::new(max.x.min(bound), max.y.min(bound)),
+        )
+    }
+
+    /// The same circles in a space of curvature `curvature`, with each centre
+    /// the same distance from the origin in the same direction and each
+    /// radius the same. Moving through curvatures bit by bit morphs the
+    /// puzzle smoothly from one geometry to another.
+    ///
+    /// Centres too far away to fit on a sphere are brought in to just short
+    /// of the antipode, since any further would wrap round to the other side.
+    pub fn with_curvature(&self, curvature: Curvature) -> Self {
+        let farthest = FARTHEST_CENTRE * curvature.antipodal_dist();
+        let move_circle = |circle: &RotCircle| {
+            let mut circle = circle.clone();
+            let r = circle.circle.cen.dist(&Pos::ORIGIN);
+            if r > 0. {
+                let distance = self.curvature.dist_from_origin(r).min(farthest);
+                let scale = curvature.model_radius(distance) / r;
+                circle.circle.cen = scale * circle.circle.cen;
+            }
+            circle.circle.curvature = curvature;
+            circle
+        };
+        let circles = self
+            .circles
+            .iter()
+            .map(|circle| RotCircle {
+                linked: circle.linked.iter().map(move_circle).collect(),
+                ..move_circle(circle)
+            })
+            .collect();
+        Self::new(circles, curvature)
+    }
+
+    /// The same circles moved onto a ring placed as by [`gen_circles`],
+    /// with everything else about them kept and their linked circles moving
+    /// along with them.
+    pub fn with_ring_distance(&self, distance: f64) -> Self {
+        let ring = gen_circles(self.circles.len(), distance, self.curvature);
+        let circles = self
+            .circles
+            .iter()
+            .zip(&ring)
+            .map(|(circle, place)| circle.moved_to(place.circle.cen))
+            .collect();
+        Self::new(circles, self.curvature)
+    }
+
+    /// Adds a circle at `cen` with the same radius and step as
+    /// [`gen_circles`] uses.
+    pub fn add_circle(&mut self, cen: Pos) {
+        self.circles
+            .push(RotCircle::new(cen, 0.5, 5, self.curvature, false));
+    }
+
+    /// Links circle `j` to circle `i`, so that it turns along with it as part
+    /// of a single generator. Circles linked to `j` are linked to `i` too.
+    /// Returns `false`, changing nothing, if the circles overlap, since a
+    /// point in both would have to turn two ways at once.
+    pub fn link(&mut self, i: usize, j: usize) -> bool {
+        if i == j || i.max(j) >= self.circles.len() || self.circles[i].overlaps(&self.circles[j]) {
+            return false;
+        }
+        let mut circle = self.circles.remove(j);
+        let i = if j < i { i - 1 } else { i };
+        let linked = std::mem::take(&mut circle.linked);
+        self.circles[i].linked.push(circle);
+        self.circles[i].linked.extend(linked);
+        true
+    }
+
+    /// Each generator's move forwards and backwards, for turning many points
+    /// with [`Move::turn`].
+    pub fn turns(&self) -> Vec<[Turn<'_>; 2]> {
+        self.circles
+            .iter()
+            .map(|circle| [circle.turn(1.), circle.turn(-1.)])
+            .collect()
+    }
+
+    /// The product of what each of `moves` does to `point`, following it
+    /// as it moves: the transformation of the component holding it, if any.
+    /// This takes the region around `point` to where the moves leave it.
+    /// Returns `None` if a move's generator doesn't exist.
+    pub fn transform(&self, point: Pos, moves: &[Move]) -> Option<MobiusTransform> {
+        let mut point = point;
+        moves.iter().try_fold(MobiusTransform::IDENT, |acc, mv| {
+            let circle = self.circles.get(mv.generator)?;
+            let Some(component) = circle.component_at(&point) else {
+                return Some(acc);
+            };
+            let step = component.transform(mv.direction());
+            point = step.apply_to(point);
+            Some(step * acc)
+        })
+    }
+
+    /// Makes the circles linked to circle `i` generators of their own again,
+    /// after the others.
+    pub fn unlink(&mut self, i: usize) {
+        let linked = std::mem::take(&mut self.circles[i].linked);
+        self.circles.extend(linked);
+    }
+}
+
+/// Places `n` circles of radius 0.5 and step 5 evenly on a ring, with
+/// `distance` being the distance in space between opposite centres.
+pub fn gen_circles(n: usize, distance: f64, curvature: Curvature) -> Vec<RotCircle> {
+    let ang = std::f64::consts::TAU / n as f64;
+    let angs = (0..n).map(|n| n as f64 * ang).collect_vec();
+    let distance = curvature.model_radius(distance / 2.);
+    angs.iter()
+        .map(|ang| {
+            RotCircle::new(
+                distance * Pos::new(-ang.cos(), ang.sin()),
+                0.5,
+                5,
+                curvature,
+                false,
+            )
+        })
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geom::{Curvature, Pos, RotCircle};
+    use crate::puzzle::Explorer;
+
+    use super::PuzzleDefinition;
+
+    fn circle(x: f64, rad: f64, step: u32) -> RotCircle {
+        RotCircle::new(Pos::new(x, 0.), rad, step, Curvature::EUCLIDEAN, false)
+    }
+
+    #[test]
+    fn link_rejects_overlaps() {
+        let mut puzzle = PuzzleDefinition::new(
+            vec![
+                circle(-0.5, 0.4, 5),
+                circle(0.5, 0.4, 3),
+                circle(0.2, 0.4, 4),
+            ],
+            Curvature::EUCLIDEAN,
+        );
+        assert!(!puzzle.link(1, 2));
+        assert_eq!(puzzle.circles.len(), 3);
+        assert!(!puzzle.link(0, 2));
+        assert!(puzzle.link(0, 1));
+        assert_eq!(puzzle.circles.len(), 2);
+        assert_eq!(puzzle.circles[0].linked.len(), 1);
+        // Anything overlapping a linked circle overlaps the generator
+        assert!(!puzzle.link(0, 1));
+    }
+
+    #[test]
+    fn link_allows_disc_in_hole() {
+        let mut ring = circle(0., 0.6, 6);
+        ring.hole_rad = 0.3;
+        let mut puzzle =
+            PuzzleDefinition::new(vec![ring, circle(0.05, 0.2, 3)], Curvature::EUCLIDEAN);
+        assert!(puzzle.link(0, 1));
+
+        let mut inverted = circle(0., 0.3, 2);
+        inverted.inverted = true;
+        puzzle.circles.push(inverted);
+        assert!(!puzzle.link(0, 1));
+    }
+
+    #[test]
+    fn orbit_words_reach_their_points() {
+        let mut puzzle = PuzzleDefinition::regular(3, 1., Curvature::EUCLIDEAN);
+        puzzle.circles[0].hole_rad = 0.2;
+        let explorer = Explorer::new(puzzle, 500);
+        let seed = Pos::new(0.05, 0.1);
+        let orbit = explorer.orbit(seed);
+        assert!(orbit.len() > 1);
+        for (i, &(point, _)) in orbit.points.iter().enumerate() {
+            let product = explorer.puzzle.transform(seed, &orbit.word(i)).unwrap();
+            assert!(product.apply_to(seed).dist(&point) < 1e-9);
+        }
+    }
+
+    #[test]
+    fn linked_turns_undo() {
+        let mut puzzle = PuzzleDefinition::new(
+            vec![circle(-0.5, 0.4, 5), circle(0.5, 0.4, 3)],
+            Curvature::EUCLIDEAN,
+        );
+        assert!(puzzle.link(0, 1));
+        let generator = &puzzle.circles[0];
+        for point in [Pos::new(-0.6, 0.1), Pos::new(0.45, -0.2), Pos::new(0., 0.3)] {
+            for fraction in [1., 0.3, -0.7] {
+                let turned = generator.turn_point(point, fraction);
+                let back = generator.turn_point(turned, -fraction);
+                assert!(back.dist(&point) < 1e-12, "{point:?} came back as {back:?}");
+            }
+        }
+        // Each linked circle turns by its own angle
+        let point = Pos::new(0.6, 0.);
+        let turned = generator.rotate_point(point);
+        let angle = |p: Pos| (p.y).atan2(p.x - 0.5);
+        let turn = (angle(turned) - angle(point)).abs();
+        assert!((turn - std::f64::consts::TAU / 3.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn turns_anticlockwise_in_every_space() {
+        for k in [1., 0.3, 0., -0.3, -1.] {
+            let curvature = Curvature::new(k);
+            let circle = RotCircle::new(Pos::ORIGIN, 0.5, 4, curvature, false);
+            let turned = circle.rotate_point(Pos::new(0.1, 0.));
+            assert!(turned.x.abs() < 1e-12 && turned.y > 0., "{k}: {turned:?}");
+        }
+    }
+
+    #[test]
+    fn with_curvature_keeps_distances() {
+        let centres = [Pos::new(0.3, 0.), Pos::new(-0.2, 0.5), Pos::new(0.1, -0.7)];
+        let puzzle = PuzzleDefinition::new(
+            centres
+                .iter()
+                .map(|&cen| RotCircle::new(cen, 0.2, 5, Curvature::EUCLIDEAN, false))
+                .collect(),
+            Curvature::EUCLIDEAN,
+        );
+        for k in [1., 0.5, -0.5, -1.] {
+            let curvature = Curvature::new(k);
+            let morphed = puzzle.with_curvature(curvature);
+            for (circle, moved) in puzzle.circles.iter().zip(&morphed.circles) {
+                let before = circle.circle.cen.dist(&Pos::ORIGIN);
+                let after = moved.circle.cen.dist_in_space(&Pos::ORIGIN, curvature);
+                assert!(
+                    (before - after).abs() < 1e-12,
+                    "{k}: {before} became {after}"
+                );
+                let direction = |p: Pos| p.y.atan2(p.x);
+                assert!((direction(circle.circle.cen) - direction(moved.circle.cen)).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn with_curvature_keeps_far_centres_on_their_side() {
+        let cen = Pos::new(5., 0.);
+        let puzzle = PuzzleDefinition::new(
+            vec![RotCircle::new(cen, 0.5, 5, Curvature::EUCLIDEAN, false)],
+            Curvature::EUCLIDEAN,
+        );
+        let morphed = puzzle.with_curvature(Curvature::SPHERICAL);
+        let moved = morphed.circles[0].circle.cen;
+        assert!(moved.x.is_finite() && moved.x > 0., "{moved:?}");
+        let distance = moved.dist_in_space(&Pos::ORIGIN, Curvature::SPHERICAL);
+        assert!(distance < Curvature::SPHERICAL.antipodal_dist());
+    }
+}
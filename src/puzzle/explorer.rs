@@ -0,0 +1,255 @@
+use hypermath::collections::ApproxHashMap;
+
+use crate::geom::{Circle, Curvature, GraphicsCircle, MobiusTransform, Pos, RotCircle};
+
+use super::{Grip, GripSet, Move, Piece, PuzzleDefinition};
+
+/// The points reached from a seed point under the transformation circles.
+#[derive(Debug, Clone)]
+pub struct Orbit {
+    /// Each point along with the index of the point it was reached from
+    pub points: Vec<(Pos, usize)>,
+    /// The move reaching each point from that one, or `None` for the seed
+    pub moves: Vec<Option<Move>>,
+    /// Largest radius around each point which does not cross any circle
+    pub max_rad: f64,
+    /// Whether the search was cut off before the orbit closed
+    pub truncated: bool,
+}
+impl Orbit {
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Whether `point` lies in the region stamped around any point of the
+    /// orbit, and so has the same orbit.
+    pub fn covers(&self, point: &Pos, curvature: Curvature) -> bool {
+        self.points
+            .iter()
+            .any(|(p, _)| p.dist_in_space(point, curvature) < self.max_rad)
+    }
+
+    /// The moves taking the seed to the point at `index`, along the path
+    /// the search found it by.
+    pub fn word(&self, mut index: usize) -> Vec<Move> {
+        let mut word = vec![];
+        while let Some(mv) = self.moves[index] {
+            word.push(mv);
+            index = self.points[index].1;
+        }
+        word.reverse();
+        word
+    }
+
+    /// Fill colour for regions in this orbit, grey if the orbit was truncated.
+    pub fn colour(&self) -> [f32; 4] {
+        if self.truncated {
+            return [0.5, 0.5, 0.5, 1.];
+        }
+        hashed_colour(&(self.points.len() + 1))
+    }
+}
+
+/// Searches the orbits of a puzzle up to a fixed depth.
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    pub puzzle: PuzzleDefinition,
+    /// Maximum number of points to expand before giving up on an orbit
+    pub depth: u32,
+}
+impl Explorer {
+    pub fn new(puzzle: PuzzleDefinition, depth: u32) -> Self {
+        Self { puzzle, depth }
+    }
+
+    fn point_max_rad(&self, point: Pos) -> f64 {
+        self.puzzle
+            .circles
+            .iter()
+            .flat_map(|c| c.boundaries())
+            .map(|c| c.dist_to_boundary(&point))
+            .reduce(f64::min)
+            .expect("Oops, no circles")
+    }
+
+    pub fn orbit(&self, seed: Pos) -> Orbit {
+        self.search_orbit(seed, |_, _, _| ())
+    }
+
+    /// Searches the orbit of `seed`, calling `checkpoint` with the number of
+    /// points expanded, the number found and the current `max_rad` each time
+    /// the number expanded reaches a power of two.
+    pub(crate) fn search_orbit(
+        &self,
+        seed: Pos,
+        mut checkpoint: impl FnMut(usize, usize, f64),
+    ) -> Orbit {
+        let mut max_rad = self.point_max_rad(seed);
+        let mut points = vec![(seed, 0)];
+        let mut moves = vec![None];
+        let mut pointset: ApproxHashMap<Pos, ()> = ApproxHashMap::new();
+        pointset.insert(&seed, ());
+        let turns = self.puzzle.turns();
+        for i in 0..self.depth as usize {
+            if i >= points.len() {
+                break;
+            }
+            // Turning both ways, since a circle turning by an irrational
+            // angle never comes back round to the inverse
+            let point = points[i].0;
+            for (generator, pair) in turns.iter().enumerate() {
+                for (turn, inverse) in pair.iter().zip([false, true]) {
+                    let Some(new) = turn.apply(point) else {
+                        continue;
+                    };
+                    if pointset.insert(&new, ()).is_none() {
+                        points.push((new, i));
+                        moves.push(Some(Move { generator, inverse }));
+                        max_rad = max_rad.min(self.point_max_rad(new));
+                    }
+                }
+            }
+            if (i + 1).is_power_of_two() {
+                checkpoint(i + 1, points.len(), max_rad);
+            }
+        }
+        let truncated = points.len() as u32 > self.depth;
+        Orbit {
+            points,
+            moves,
+            max_rad,
+            truncated,
+        }
+    }
+
+    /// Points on a `grid` by `grid` grid over the bounds of the circles which
+    /// lie in space and inside some circle, to seed orbit searches from. This
+    /// covers every circle whichever way the view is turned, though pieces
+    /// smaller than a grid cell may be missed.
+    pub fn seeds(&self, grid: usize) -> impl Iterator<Item = Pos> + '_ {
+        let curvature = self.puzzle.curvature;
+        let (min, max) = self.puzzle.bounds();
+        (0..grid * grid)
+            .map(move |i| {
+                let t = |k: usize| (k as f64 + 0.5) / grid as f64;
+                Pos::new(
+                    min.x + (max.x - min.x) * t(i % grid),
+                    min.y + (max.y - min.y) * t(i / grid),
+                )
+            })
+            .filter(move |seed| {
+                seed.is_in_space(curvature) && self.puzzle.circles.iter().any(|c| c.contains(seed))
+            })
+    }
+
+    /// Size and truncation of each orbit meeting a circle, found by seeding
+    /// from a grid over the circles and skipping seeds inside regions already
+    /// stamped, like autofill does. Stops after the first truncated orbit,
+    /// since truncated orbits are slow to find and stamp.
+    pub fn census(&self) -> Vec<(usize, bool)> {
+        const GRID: usize = 32;
+        let curvature = self.puzzle.curvature;
+        let mut stamps: Vec<(Pos, f64)> = vec![];
+        let mut ret = vec![];
+        for seed in self.seeds(GRID) {
+            if stamps
+                .iter()
+                .any(|(p, r)| p.dist_in_space(&seed, curvature) < *r)
+            {
+                continue;
+            }
+            let orbit = self.orbit(seed);
+            stamps.extend(orbit.points.iter().map(|&(p, _)| (p, orbit.max_rad)));
+            ret.push((orbit.len(), orbit.truncated));
+            if orbit.truncated {
+                break;
+            }
+        }
+        ret
+    }
+
+    /// Stamps a circle around each point in the orbit of `seed`, as seen
+    /// through `camera`.
+    pub fn expand_seed(
+        &self,
+        seed: Pos,
+        camera: &MobiusTransform,
+        circles: &mut Vec<GraphicsCircle>,
+    ) {
+        let curvature = self.puzzle.curvature;
+        let orbit = self.orbit(seed);
+        let col = orbit.colour();
+        for point in &orbit.points {
+            let shape = Circle::new(point.0, orbit.max_rad, curvature).shape(camera);
+            circles.push(GraphicsCircle::from_shape(shape, col));
+        }
+    }
+
+    /// Every arrangement of the circles reachable by rotating circles which
+    /// contain `seed`.
+    pub fn grip_sets(&self, seed: Pos) -> Vec<GripSet> {
+        let base_grips = GripSet {
+            circles: self.puzzle.circles.clone(),
+        };
+        let mut gripsets = vec![base_grips.clone()];
+        let mut gripset_set: ApproxHashMap<GripSet, ()> = ApproxHashMap::new();
+        gripset_set.insert(&base_grips, ());
+
+        for i in 0..self.depth as usize {
+            if i >= gripsets.len() {
+                break;
+            }
+            for j in 0..gripsets[i].circles.len() {
+                if let Some(component) = gripsets[i].circles[j].component_at(&seed).cloned() {
+                    for inverse in [false, true] {
+                        let new_set = gripsets[i].rotate_by(&component, inverse);
+                        if gripset_set.insert(&new_set, ()).is_none() {
+                            gripsets.push(new_set);
+                        }
+                    }
+                }
+            }
+        }
+        gripsets
+    }
+
+    /// The piece containing `seed`, described by every grip which can turn it.
+    pub fn piece(&self, seed: Pos) -> Piece {
+        let mut grips = vec![];
+        let mut piece_grip_set: ApproxHashMap<RotCircle, ()> = ApproxHashMap::new();
+        for gripset in self.grip_sets(seed) {
+            for (i, grip) in gripset.circles.iter().enumerate() {
+                let Some(k) = grip.component_index(&seed) else {
+                    continue;
+                };
+                let component = grip.components().nth(k).expect("Index was just found");
+                if piece_grip_set.insert(component, ()).is_none() {
+                    grips.push(Grip::new(component.circle.cen, i, k, component.circle.kind));
+                }
+            }
+        }
+        Piece::new(grips)
+    }
+}
+
+/// A colour picked from the sinebow by hashing `t`.
+pub(crate) fn hashed_colour<T: std::hash::Hash>(t: &T) -> [f32; 4] {
+    let col =
+        colorous::SINEBOW.eval_rational(calculate_hash(t) as u32 as usize, u32::MAX as usize + 1);
+    [
+        col.r as f32 / 255.,
+        col.g as f32 / 255.,
+        col.b as f32 / 255.,
+        1.,
+    ]
+}
+
+fn calculate_hash<T: std::hash::Hash>(t: &T) -> u64 {
+    let mut s = std::hash::DefaultHasher::new();
+    t.hash(&mut s);
+    std::hash::Hasher::finish(&s)
+}
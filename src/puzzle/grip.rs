@@ -1,12 +1,91 @@
-use crate::geom::Pos;
+use hypermath::collections::approx_hashmap::{ApproxHashMapKey, FloatHash};
+use itertools::Itertools;
+
+use crate::geom::{CircleKind, Pos, RotCircle};
+
+use super::PuzzleDefinition;
 
 #[derive(Debug, Clone)]
-pub(crate) struct Grip {
+pub struct Grip {
     pub pos: Pos,
     pub id: usize,
+    /// Which of the generator's [`RotCircle::components`] is at `pos`
+    pub component: usize,
+    /// The component's kind where it has been moved to, whose ideal point
+    /// moves along with its centre
+    pub kind: CircleKind,
 }
 impl Grip {
-    pub fn new(pos: Pos, id: usize) -> Self {
-        Self { pos, id }
+    pub fn new(pos: Pos, id: usize, component: usize, kind: CircleKind) -> Self {
+        Self {
+            pos,
+            id,
+            component,
+            kind,
+        }
+    }
+
+    /// The component moved to the grip, whose boundaries are the cuts it
+    /// makes, or `None` if the circle is gone.
+    pub fn cut(&self, puzzle: &PuzzleDefinition) -> Option<RotCircle> {
+        let circle = puzzle.circles.get(self.id)?;
+        let mut cut = circle
+            .components()
+            .nth(self.component)?
+            .with_centre(self.pos);
+        cut.circle.kind = self.kind;
+        Some(cut)
+    }
+}
+
+/// The transformation circles after some sequence of rotations, indexed the
+/// same as the original circles.
+#[derive(Debug, Clone)]
+pub struct GripSet {
+    pub circles: Vec<RotCircle>,
+}
+impl GripSet {
+    /// Moves every circle by the rotation of `component`, the opposite way if
+    /// `inverse`.
+    pub fn rotate_by(&self, component: &RotCircle, inverse: bool) -> Self {
+        let circles = self
+            .circles
+            .iter()
+            .map(|circle| component.rotate_circle(circle, inverse))
+            .collect_vec();
+        Self { circles }
+    }
+}
+impl ApproxHashMapKey for GripSet {
+    type Hash = Vec<(
+        <Pos as ApproxHashMapKey>::Hash,
+        Option<FloatHash>,
+        Option<<Pos as ApproxHashMapKey>::Hash>,
+    )>;
+
+    /// Hashes where each component is, which way its mirror lies and the
+    /// direction of its ideal point, since arrangements differing only in
+    /// those move to different ones.
+    fn approx_hash(
+        &self,
+        mut float_hash_fn: impl FnMut(hypermath::prelude::Float) -> FloatHash,
+    ) -> Self::Hash {
+        self.circles
+            .iter()
+            .flat_map(|circle| circle.components())
+            .map(|circle| {
+                (
+                    circle.circle.cen.approx_hash(&mut float_hash_fn),
+                    circle
+                        .mirror
+                        .map(|angle| float_hash_fn(angle.rem_euclid(std::f64::consts::PI))),
+                    circle
+                        .circle
+                        .kind
+                        .ideal()
+                        .map(|ideal| ideal.approx_hash(&mut float_hash_fn)),
+                )
+            })
+            .collect()
     }
 }
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+
+use hypermath::collections::ApproxHashMap;
+use itertools::Itertools;
+
+use crate::geom::Pos;
+use crate::group::{GroupKind, Permutation, StabChain};
+
+use super::{Explorer, Orbit};
+
+/// The transformation circles acting as permutations on a finite set of
+/// pieces, each piece represented by a point inside it.
+#[derive(Debug, Clone)]
+pub struct PieceGroup {
+    pub pieces: Vec<Pos>,
+    /// Piece indices in each orbit
+    pub orbits: Vec<Vec<usize>>,
+    /// One permutation per transformation circle
+    pub generators: Vec<Permutation>,
+    /// The inverse of each generator
+    pub inverses: Vec<Permutation>,
+    pub chain: StabChain,
+}
+impl PieceGroup {
+    /// The group generated by the circles, restricted to a single orbit.
+    pub fn orbit_chain(&self, orbit: usize) -> StabChain {
+        let points = &self.orbits[orbit];
+        let gens = self
+            .generators
+            .iter()
+            .map(|g| g.restrict(points))
+            .collect_vec();
+        StabChain::new(points.len(), &gens)
+    }
+
+    pub fn orbit_kind(&self, orbit: usize) -> GroupKind {
+        self.orbit_chain(orbit).kind()
+    }
+}
+
+impl Explorer {
+    /// Each circle as a permutation of the points of `orbit`, in the order
+    /// they were found. Returns `None` if a rotated point can't be found in
+    /// the orbit, which only happens if the orbit was truncated.
+    pub fn orbit_action(&self, orbit: &Orbit) -> Option<Vec<Permutation>> {
+        let mut point_ids: ApproxHashMap<Pos, u32> = ApproxHashMap::new();
+        for (i, (point, _)) in orbit.points.iter().enumerate() {
+            point_ids.insert(point, i as u32);
+        }
+        self.puzzle
+            .circles
+            .iter()
+            .map(|circle| {
+                let turn = circle.turn(1.);
+                let images = orbit
+                    .points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &(point, _))| match turn.apply(point) {
+                        Some(moved) => point_ids.get(&moved).copied(),
+                        None => Some(i as u32),
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Permutation::from_images(images)
+            })
+            .collect()
+    }
+
+    /// Collects the orbits of `seeds` into a piece group. Seeds which land
+    /// inside an already-stamped region are skipped, like they are when
+    /// autofilling, and orbits on which the circles act the same way as on
+    /// an earlier orbit are merged into it.
+    ///
+    /// Returns `None` if any orbit was truncated, in which case the puzzle
+    /// is jumbling or `depth` is too small.
+    pub fn piece_group(&self, seeds: impl IntoIterator<Item = Pos>) -> Option<PieceGroup> {
+        let curvature = self.puzzle.curvature;
+        let mut pieces: Vec<Pos> = vec![];
+        let mut orbits = vec![];
+        let mut images = vec![vec![]; self.puzzle.circles.len()];
+        let mut stamps: Vec<(Pos, f64)> = vec![];
+        let mut actions = HashSet::new();
+        for seed in seeds {
+            if stamps
+                .iter()
+                .any(|(p, r)| p.dist_in_space(&seed, curvature) < *r)
+            {
+                continue;
+            }
+            let orbit = self.orbit(seed);
+            if orbit.truncated {
+                return None;
+            }
+            stamps.extend(orbit.points.iter().map(|&(p, _)| (p, orbit.max_rad)));
+            let action = self.orbit_action(&orbit)?;
+            if actions.contains(&action) {
+                continue;
+            }
+            let offset = pieces.len();
+            for (gen_images, perm) in images.iter_mut().zip(&action) {
+                gen_images.extend(perm.images().iter().map(|&i| i + offset as u32));
+            }
+            actions.insert(action);
+            orbits.push((offset..offset + orbit.len()).collect_vec());
+            pieces.extend(orbit.points.iter().map(|&(p, _)| p));
+        }
+
+        let generators = images
+            .into_iter()
+            .map(Permutation::from_images)
+            .collect::<Option<Vec<_>>>()?;
+        let inverses = generators.iter().map(Permutation::inverse).collect();
+        let chain = StabChain::new(pieces.len(), &generators);
+        Some(PieceGroup {
+            pieces,
+            orbits,
+            generators,
+            inverses,
+            chain,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::collections::HashSet;
+
+    use crate::geom::{Curvature, Pos, RotCircle};
+    use crate::group::Permutation;
+    use crate::puzzle::{Explorer, PuzzleDefinition};
+
+    use super::PieceGroup;
+
+    /// The group of `count` circles of radius `rad` turning by `1/step` of a
+    /// turn, spaced around a ring of diameter 1.
+    pub(in crate::puzzle) fn regular_group(count: usize, step: u32, rad: f64) -> PieceGroup {
+        let mut puzzle = PuzzleDefinition::regular(count, 1., Curvature::EUCLIDEAN);
+        for circle in &mut puzzle.circles {
+            circle.step = step;
+            circle.circle.rad = rad;
+        }
+        let explorer = Explorer::new(puzzle, 3000);
+        explorer
+            .piece_group(explorer.seeds(64))
+            .expect("Orbits were truncated")
+    }
+
+    /// Number of elements of the group generated by `generators`, found by
+    /// listing them all.
+    fn closure_order(generators: &[Permutation]) -> u128 {
+        let identity = Permutation::identity(generators[0].degree());
+        let mut seen = HashSet::from([identity.clone()]);
+        let mut queue = vec![identity];
+        while let Some(perm) = queue.pop() {
+            for g in generators {
+                let next = perm.then(g);
+                if seen.insert(next.clone()) {
+                    queue.push(next);
+                }
+            }
+        }
+        seen.len() as u128
+    }
+
+    #[test]
+    fn single_circle_is_cyclic() {
+        let puzzle = PuzzleDefinition::new(
+            vec![RotCircle::new(
+                Pos::ORIGIN,
+                0.5,
+                5,
+                Curvature::EUCLIDEAN,
+                false,
+            )],
+            Curvature::EUCLIDEAN,
+        );
+        let explorer = Explorer::new(puzzle, 100);
+        let group = explorer.piece_group(explorer.seeds(16)).unwrap();
+        assert_eq!(group.chain.order(), Some(5));
+        assert_eq!(group.generators[0].order(), 5);
+    }
+
+    #[test]
+    fn orders_match_closure() {
+        for (count, step, rad, order) in [(3, 2, 0.6, 2592), (2, 3, 0.55, 540)] {
+            let group = regular_group(count, step, rad);
+            assert_eq!(group.chain.order(), Some(order));
+            assert_eq!(closure_order(&group.generators), order);
+        }
+    }
+}
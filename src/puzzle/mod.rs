@@ -1,5 +1,18 @@
+mod classify;
+mod commutator;
+mod definition;
+mod explorer;
 mod grip;
+mod group;
 mod piece;
+mod solve;
+mod state;
 
-pub(crate) use grip::Grip;
-pub(crate) use piece::Piece;
+pub use classify::OrbitClass;
+pub use commutator::Algorithm;
+pub use definition::{gen_circles, PuzzleDefinition};
+pub use explorer::{Explorer, Orbit};
+pub use grip::{Grip, GripSet};
+pub use group::PieceGroup;
+pub use piece::Piece;
+pub use state::{ease, Move, ParseMoveError, PuzzleState, Region, Twist};
@@ -1,7 +1,7 @@
 use super::grip::Grip;
 
 #[derive(Debug, Clone)]
-pub(crate) struct Piece {
+pub struct Piece {
     grips: Vec<Grip>,
 }
 impl Piece {
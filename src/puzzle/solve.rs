@@ -0,0 +1,185 @@
+use itertools::Itertools;
+
+use crate::group::Permutation;
+
+use super::{Move, PieceGroup};
+
+impl PieceGroup {
+    /// The permutation taking each piece from its solved position to where
+    /// `moves` leave it. Returns `None` if a move's generator doesn't exist.
+    pub fn permutation(&self, moves: &[Move]) -> Option<Permutation> {
+        let mut ret = Permutation::identity(self.pieces.len());
+        for mv in moves {
+            let turn = match mv.inverse {
+                true => self.inverses.get(mv.generator)?,
+                false => self.generators.get(mv.generator)?,
+            };
+            ret.then_assign(turn);
+        }
+        Some(ret)
+    }
+
+    /// Whether every piece is back where it started after `moves`.
+    pub fn is_solved(&self, moves: &[Move]) -> bool {
+        self.permutation(moves)
+            .is_some_and(|perm| perm.is_identity())
+    }
+
+    /// Finds a shortest sequence of moves which solves the puzzle after
+    /// `moves`, by iterative deepening A* on the piece permutation. The
+    /// search gives up after expanding `max_nodes` positions.
+    ///
+    /// The heuristic is the number of misplaced pieces divided by the most
+    /// pieces any one generator moves, which never overestimates, so the
+    /// solution found is optimal in the number of moves.
+    pub fn solve(&self, moves: &[Move], max_nodes: usize) -> Option<Vec<Move>> {
+        let start = self.permutation(moves)?;
+        if start.is_identity() {
+            return Some(vec![]);
+        }
+        let max_moved = self
+            .generators
+            .iter()
+            .map(|g| g.support().count())
+            .max()
+            .filter(|&n| n > 0)?;
+        let turns = (0..self.generators.len())
+            .flat_map(|generator| [false, true].map(|inverse| Move { generator, inverse }))
+            .map(|mv| {
+                let perm = match mv.inverse {
+                    true => &self.inverses[mv.generator],
+                    false => &self.generators[mv.generator],
+                };
+                (mv, perm.clone())
+            })
+            .collect_vec();
+        let orders = self.generators.iter().map(Permutation::order).collect_vec();
+        let mut search = Search {
+            turns,
+            orders,
+            max_moved,
+            nodes: 0,
+            max_nodes,
+            path: vec![],
+        };
+        let mut bound = search.heuristic(&start);
+        loop {
+            match search.search(&start, bound) {
+                Bound::Found => return Some(search.path),
+                Bound::Next(next) => bound = next,
+                Bound::Exhausted => return None,
+            }
+        }
+    }
+}
+
+enum Bound {
+    Found,
+    /// The smallest estimated total cost which exceeded the bound
+    Next(usize),
+    /// Nothing exceeded the bound, or the node limit was reached
+    Exhausted,
+}
+
+struct Search {
+    /// Each generator turned each way, with its permutation
+    turns: Vec<(Move, Permutation)>,
+    /// Order of each generator
+    orders: Vec<usize>,
+    max_moved: usize,
+    nodes: usize,
+    max_nodes: usize,
+    path: Vec<Move>,
+}
+impl Search {
+    fn heuristic(&self, perm: &Permutation) -> usize {
+        perm.support().count().div_ceil(self.max_moved)
+    }
+
+    /// Whether `mv` is worth trying after the current path. Runs of one
+    /// generator keep to one direction and stop at half a revolution, so
+    /// each power of it is only tried once.
+    fn allowed(&self, mv: Move) -> bool {
+        let run = self
+            .path
+            .iter()
+            .rev()
+            .take_while(|m| m.generator == mv.generator)
+            .collect_vec();
+        if run.first().is_some_and(|m| m.inverse != mv.inverse) {
+            return false;
+        }
+        let order = self.orders[mv.generator];
+        let max_run = match mv.inverse {
+            true => (order - 1) / 2,
+            false => order / 2,
+        };
+        run.len() < max_run
+    }
+
+    fn search(&mut self, perm: &Permutation, bound: usize) -> Bound {
+        let estimate = self.path.len() + self.heuristic(perm);
+        if estimate > bound {
+            return Bound::Next(estimate);
+        }
+        if perm.is_identity() {
+            return Bound::Found;
+        }
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            return Bound::Exhausted;
+        }
+        let mut next = None;
+        for i in 0..self.turns.len() {
+            let mv = self.turns[i].0;
+            if !self.allowed(mv) {
+                continue;
+            }
+            let child = perm.then(&self.turns[i].1);
+            self.path.push(mv);
+            match self.search(&child, bound) {
+                Bound::Found => return Bound::Found,
+                Bound::Next(n) => next = Some(next.map_or(n, |m: usize| m.min(n))),
+                Bound::Exhausted if self.nodes > self.max_nodes => return Bound::Exhausted,
+                Bound::Exhausted => {}
+            }
+            self.path.pop();
+        }
+        next.map_or(Bound::Exhausted, Bound::Next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::puzzle::group::tests::regular_group;
+    use crate::puzzle::Move;
+
+    /// Parses moves written as in a move log, separated by spaces.
+    fn moves(s: &str) -> Vec<Move> {
+        s.split_whitespace().map(|mv| mv.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn solves_scrambles() {
+        for (count, step, rad, scramble) in
+            [(3, 2, 0.6, "0 1 2 0 1"), (2, 3, 0.55, "0 1' 0 1 1 0'")]
+        {
+            let group = regular_group(count, step, rad);
+            let scramble = moves(scramble);
+            assert!(!group.is_solved(&scramble));
+            let solution = group.solve(&scramble, 1_000_000).unwrap();
+            assert!(solution.len() <= scramble.len());
+            let mut all = scramble.clone();
+            all.extend(&solution);
+            assert!(group.is_solved(&all));
+        }
+    }
+
+    #[test]
+    fn solved_needs_no_moves() {
+        let group = regular_group(3, 2, 0.6);
+        assert!(group.is_solved(&[]));
+        assert_eq!(group.solve(&moves("0 0"), 10), Some(vec![]));
+        assert_eq!(group.permutation(&moves("3")), None);
+    }
+}
@@ -0,0 +1,430 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::geom::{Circle, Curvature, GraphicsCircle, MobiusTransform, Pos, RotCircle, Turn};
+
+use super::{Explorer, PuzzleDefinition};
+
+/// A disc which lies inside or outside every circle, and so moves as part
+/// of a single piece.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub pos: Pos,
+    pub rad: f64,
+    pub col: [f32; 4],
+}
+impl Region {
+    /// The disc as seen through `camera`.
+    pub fn graphics_circle(
+        &self,
+        camera: &MobiusTransform,
+        curvature: Curvature,
+    ) -> GraphicsCircle {
+        let shape = Circle::new(self.pos, self.rad, curvature).shape(camera);
+        GraphicsCircle::from_shape(shape, self.col)
+    }
+}
+
+/// A turn of one generator circle by one step, written as the generator
+/// index followed by `'` if inverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Move {
+    /// Index of the circle, the same as [`super::Grip::id`]
+    pub generator: usize,
+    /// Whether to turn the opposite way
+    pub inverse: bool,
+}
+impl Move {
+    /// The move which undoes this one.
+    pub fn reversed(self) -> Self {
+        Self {
+            inverse: !self.inverse,
+            ..self
+        }
+    }
+
+    /// Fraction of a move of the generator turned: 1, or -1 if inverse.
+    pub fn direction(self) -> f64 {
+        match self.inverse {
+            true => -1.,
+            false => 1.,
+        }
+    }
+
+    /// This move among `turns` from [`PuzzleDefinition::turns`], or `None`
+    /// if the generator doesn't exist.
+    pub fn turn<'a, 'b>(self, turns: &'a [[Turn<'b>; 2]]) -> Option<&'a Turn<'b>> {
+        let [forwards, backwards] = turns.get(self.generator)?;
+        Some(match self.inverse {
+            true => backwards,
+            false => forwards,
+        })
+    }
+
+    /// Where `point` ends up, or `None` if the generator doesn't exist.
+    pub fn apply_to(self, puzzle: &PuzzleDefinition, point: Pos) -> Option<Pos> {
+        let circle = puzzle.circles.get(self.generator)?;
+        Some(circle.turn_point(point, self.direction()))
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.generator)?;
+        if self.inverse {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (generator, inverse) = match s.strip_suffix('\'') {
+            Some(generator) => (generator, true),
+            None => (s, false),
+        };
+        let generator = generator
+            .parse()
+            .map_err(|_| ParseMoveError(s.to_string()))?;
+        Ok(Self { generator, inverse })
+    }
+}
+impl From<Move> for String {
+    fn from(mv: Move) -> Self {
+        mv.to_string()
+    }
+}
+impl TryFrom<String> for Move {
+    type Error = ParseMoveError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseMoveError(String);
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid move {:?}", self.0)
+    }
+}
+impl std::error::Error for ParseMoveError {}
+
+/// A twist in progress, which turns the regions it affects gradually.
+#[derive(Debug, Clone)]
+pub struct Twist {
+    pub mv: Move,
+    /// Index and starting position of each affected region
+    starts: Vec<(usize, Pos)>,
+    /// Number of regions when the twist began. Regions added since have not
+    /// been moved by it.
+    region_count: usize,
+}
+impl Twist {
+    /// Indices of the regions being turned.
+    pub fn regions(&self) -> impl Iterator<Item = usize> + '_ {
+        self.starts.iter().map(|&(i, _)| i)
+    }
+}
+
+/// Smoothstep easing, taking the fraction of time elapsed to the fraction of
+/// the angle turned.
+pub fn ease(t: f64) -> f64 {
+    let t = t.clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+/// The regions found so far, moved by a sequence of twists.
+#[derive(Debug, Clone, Default)]
+pub struct PuzzleState {
+    pub regions: Vec<Region>,
+    /// Every twist applied since the solved state
+    pub moves: Vec<Move>,
+    /// Undone twists, the next to redo last
+    pub undone: Vec<Move>,
+}
+impl PuzzleState {
+    /// Adds regions given in their solved positions, moving them by every
+    /// twist so far. Returns the added regions in their current positions.
+    pub fn add_regions(
+        &mut self,
+        puzzle: &PuzzleDefinition,
+        regions: impl IntoIterator<Item = Region>,
+    ) -> &[Region] {
+        let start = self.regions.len();
+        for mut region in regions {
+            for &mv in &self.moves {
+                region.pos = mv.apply_to(puzzle, region.pos).unwrap_or(region.pos);
+            }
+            self.regions.push(region);
+        }
+        &self.regions[start..]
+    }
+
+    /// Applies a twist to every region, unless the generator doesn't exist.
+    pub fn twist(&mut self, puzzle: &PuzzleDefinition, mv: Move) -> bool {
+        match self.begin_twist(puzzle, mv) {
+            Some(twist) => {
+                self.finish_twist(puzzle, twist);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Starts a twist without moving anything, unless the generator doesn't
+    /// exist.
+    pub fn begin_twist(&self, puzzle: &PuzzleDefinition, mv: Move) -> Option<Twist> {
+        let circle = puzzle.circles.get(mv.generator)?;
+        let starts = self
+            .regions
+            .iter()
+            .enumerate()
+            .filter(|(_, region)| circle.contains(&region.pos))
+            .map(|(i, region)| (i, region.pos))
+            .collect();
+        Some(Twist {
+            mv,
+            starts,
+            region_count: self.regions.len(),
+        })
+    }
+
+    /// Turns the regions affected by `twist` through the eased fraction `t`
+    /// of its angle.
+    pub fn animate_twist(&mut self, puzzle: &PuzzleDefinition, twist: &Twist, t: f64) {
+        let Some(circle) = puzzle.circles.get(twist.mv.generator) else {
+            return;
+        };
+        let turn = circle.turn(twist.mv.direction() * ease(t));
+        for &(i, start) in &twist.starts {
+            if let Some(region) = self.regions.get_mut(i) {
+                region.pos = turn.apply(start).unwrap_or(start);
+            }
+        }
+    }
+
+    /// Reverts the last twist, returning it.
+    pub fn undo(&mut self, puzzle: &PuzzleDefinition) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        for region in &mut self.regions {
+            region.pos = mv
+                .reversed()
+                .apply_to(puzzle, region.pos)
+                .unwrap_or(region.pos);
+        }
+        self.undone.push(mv);
+        Some(mv)
+    }
+
+    /// Reapplies the last undone twist, returning it.
+    pub fn redo(&mut self, puzzle: &PuzzleDefinition) -> Option<Move> {
+        let mv = self.undone.pop()?;
+        let undone = std::mem::take(&mut self.undone);
+        self.twist(puzzle, mv);
+        self.undone = undone;
+        Some(mv)
+    }
+
+    /// Completes `twist`, including on regions added since it began. This
+    /// forgets any undone twists.
+    pub fn finish_twist(&mut self, puzzle: &PuzzleDefinition, twist: Twist) {
+        for (i, start) in twist.starts {
+            if let Some(region) = self.regions.get_mut(i) {
+                region.pos = twist.mv.apply_to(puzzle, start).unwrap_or(start);
+            }
+        }
+        for region in self.regions.iter_mut().skip(twist.region_count) {
+            region.pos = twist.mv.apply_to(puzzle, region.pos).unwrap_or(region.pos);
+        }
+        self.moves.push(twist.mv);
+        self.undone.clear();
+    }
+
+    /// Applies `count` random twists.
+    pub fn scramble(&mut self, puzzle: &PuzzleDefinition, count: usize, rng: &mut impl Rng) {
+        if puzzle.circles.is_empty() {
+            return;
+        }
+        for _ in 0..count {
+            let mv = Move {
+                generator: rng.gen_range(0..puzzle.circles.len()),
+                inverse: rng.gen(),
+            };
+            self.twist(puzzle, mv);
+        }
+    }
+}
+
+impl Explorer {
+    /// The regions stamped around each point in the orbit of `seed`, in
+    /// their solved colours. Unlike orbit colours these differ between
+    /// points of the orbit, so twisting them makes a visible change.
+    pub fn orbit_regions(&self, seed: Pos) -> Vec<Region> {
+        let orbit = self.orbit(seed);
+        orbit
+            .points
+            .iter()
+            .map(|&(pos, _)| Region {
+                pos,
+                rad: orbit.max_rad,
+                col: self.puzzle.solved_colour(pos),
+            })
+            .collect()
+    }
+
+    /// The generator to twist when clicking `point`: the one containing it
+    /// whose centre is nearest.
+    pub fn generator_at(&self, point: Pos) -> Option<usize> {
+        let curvature = self.puzzle.curvature;
+        self.puzzle
+            .circles
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.contains(&point))
+            .min_by(|(_, a), (_, b)| {
+                let dist = |c: &&RotCircle| c.circle.cen.dist_in_space(&point, curvature);
+                dist(a).total_cmp(&dist(b))
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Configuration, MoveLog};
+    use crate::geom::{Curvature, MobiusTransform, Pos};
+    use crate::puzzle::{Explorer, PuzzleDefinition};
+
+    use super::{Move, PuzzleState, Region};
+
+    fn puzzle() -> PuzzleDefinition {
+        PuzzleDefinition::regular(3, 1., Curvature::EUCLIDEAN)
+    }
+
+    fn state(puzzle: &PuzzleDefinition) -> PuzzleState {
+        let mut state = PuzzleState::default();
+        let points = [(0.01, 0.02), (-0.4, 0.1), (0.3, 0.3), (0.2, -0.5), (2., 2.)];
+        state.add_regions(
+            puzzle,
+            points.map(|(x, y)| Region {
+                pos: Pos::new(x, y),
+                rad: 0.01,
+                col: [1.; 4],
+            }),
+        );
+        state
+    }
+
+    fn positions(state: &PuzzleState) -> Vec<Pos> {
+        state.regions.iter().map(|region| region.pos).collect()
+    }
+
+    fn assert_same(a: &[Pos], b: &[Pos]) {
+        assert_eq!(a.len(), b.len());
+        for (p, q) in a.iter().zip(b) {
+            assert!(p.dist(q) < 1e-9, "{p:?} != {q:?}");
+        }
+    }
+
+    fn mv(s: &str) -> Move {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn undo_and_redo_twists() {
+        let puzzle = puzzle();
+        let mut state = state(&puzzle);
+        let solved = positions(&state);
+
+        assert!(state.twist(&puzzle, mv("0")));
+        assert!(state.twist(&puzzle, mv("1'")));
+        assert!(!state.twist(&puzzle, mv("7")));
+        assert_eq!(state.moves, [mv("0"), mv("1'")]);
+        let twisted = positions(&state);
+        assert!(solved.iter().zip(&twisted).any(|(p, q)| p.dist(q) > 1e-3));
+        // Regions outside every circle stay put
+        assert_same(&solved[4..], &twisted[4..]);
+
+        assert_eq!(state.undo(&puzzle), Some(mv("1'")));
+        assert_eq!(state.undo(&puzzle), Some(mv("0")));
+        assert_eq!(state.undo(&puzzle), None);
+        assert_same(&positions(&state), &solved);
+
+        assert_eq!(state.redo(&puzzle), Some(mv("0")));
+        assert_eq!(state.redo(&puzzle), Some(mv("1'")));
+        assert_eq!(state.redo(&puzzle), None);
+        assert_same(&positions(&state), &twisted);
+    }
+
+    #[test]
+    fn new_twist_forgets_undone() {
+        let puzzle = puzzle();
+        let mut state = state(&puzzle);
+        state.twist(&puzzle, mv("0"));
+        state.twist(&puzzle, mv("1"));
+        state.undo(&puzzle);
+        assert_eq!(state.undone, [mv("1")]);
+        state.twist(&puzzle, mv("2"));
+        assert!(state.undone.is_empty());
+        assert_eq!(state.redo(&puzzle), None);
+        assert_eq!(state.moves, [mv("0"), mv("2")]);
+    }
+
+    #[test]
+    fn finished_twist_matches_twist() {
+        let puzzle = puzzle();
+        let mut twisted = state(&puzzle);
+        twisted.twist(&puzzle, mv("0"));
+        twisted.twist(&puzzle, mv("2'"));
+
+        let mut animated = state(&puzzle);
+        animated.twist(&puzzle, mv("0"));
+        let twist = animated.begin_twist(&puzzle, mv("2'")).unwrap();
+        animated.animate_twist(&puzzle, &twist, 0.4);
+        // Found partway through, so moved only by the finished twists
+        let late = Region {
+            pos: Pos::new(-0.2, -0.3),
+            rad: 0.01,
+            col: [1.; 4],
+        };
+        animated.add_regions(&puzzle, [late.clone()]);
+        animated.finish_twist(&puzzle, twist);
+        twisted.add_regions(&puzzle, [late]);
+        assert_same(&positions(&animated), &positions(&twisted));
+        assert_eq!(animated.moves, twisted.moves);
+    }
+
+    #[test]
+    fn move_log_replays() {
+        let explorer = Explorer::new(puzzle(), 100);
+        let puzzle = &explorer.puzzle;
+        let mut played = state(puzzle);
+        for s in ["0", "1'", "2", "0", "1"] {
+            played.twist(puzzle, mv(s));
+        }
+        let log = MoveLog {
+            config: Configuration::new(&explorer, 1., &MobiusTransform::IDENT),
+            moves: played.moves.clone(),
+        };
+        let path = std::env::temp_dir().join(format!("pgs-log-{}.ron", std::process::id()));
+        log.save(&path).unwrap();
+        let loaded = MoveLog::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.moves, log.moves);
+
+        let puzzle = &loaded.config.explorer().puzzle;
+        let mut replayed = state(puzzle);
+        for &mv in &loaded.moves {
+            assert!(replayed.twist(puzzle, mv));
+        }
+        assert_same(&positions(&replayed), &positions(&played));
+    }
+}
@@ -0,0 +1,324 @@
+//! CPU rendering of the autofilled canvas, for machines without a GPU.
+
+use std::f64::consts::{PI, TAU};
+use std::{fs::File, io::BufWriter, path::Path};
+
+use bitvec::prelude::*;
+
+use crate::geom::{GraphicsCircle, MobiusTransform, Pos};
+use crate::puzzle::{Explorer, Move, PuzzleDefinition};
+
+/// Which part of the plane to render, laid out the same way as the canvas in
+/// the app: the unit circle spans `scale` times the shorter side.
+#[derive(Debug, Clone)]
+pub struct View {
+    pub width: u32,
+    pub height: u32,
+    pub scale: f64,
+    pub camera: MobiusTransform,
+}
+impl View {
+    /// Pixels per unit of screen space.
+    pub fn unit(&self) -> f64 {
+        self.width.min(self.height) as f64 * self.scale / 2.
+    }
+
+    pub fn pixel_to_screen(&self, x: f64, y: f64) -> Pos {
+        let unit = self.unit();
+        Pos::new(
+            (x - self.width as f64 / 2.) / unit,
+            -(y - self.height as f64 / 2.) / unit,
+        )
+    }
+
+    pub fn screen_to_pixel(&self, pos: Pos) -> (f64, f64) {
+        let unit = self.unit();
+        (
+            pos.x * unit + self.width as f64 / 2.,
+            -pos.y * unit + self.height as f64 / 2.,
+        )
+    }
+
+    pub fn pixel_to_geom(&self, x: f64, y: f64) -> Pos {
+        self.camera.inverse().apply_to(self.pixel_to_screen(x, y))
+    }
+}
+
+/// An RGBA image with 8 bits per channel.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 4]; width as usize * height as usize],
+        }
+    }
+
+    /// Loads a PNG of any colour type, converting it to 8-bit RGBA.
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let bytes = &buf[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Grayscale => bytes.iter().map(|&l| [l, l, l, 255]).collect(),
+            png::ColorType::GrayscaleAlpha => bytes
+                .chunks_exact(2)
+                .map(|c| [c[0], c[0], c[0], c[1]])
+                .collect(),
+            png::ColorType::Rgb => bytes
+                .chunks_exact(3)
+                .map(|c| [c[0], c[1], c[2], 255])
+                .collect(),
+            png::ColorType::Rgba => bytes
+                .chunks_exact(4)
+                .map(|c| [c[0], c[1], c[2], c[3]])
+                .collect(),
+            // Palettes are expanded by the normalisation above
+            png::ColorType::Indexed => {
+                return Err(png::DecodingError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Indexed PNG was not expanded to colours",
+                )))
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    /// The pixel at `(x, y)` in pixel coordinates, or `None` outside the
+    /// image.
+    fn pixel_at(&self, x: f64, y: f64) -> Option<[u8; 4]> {
+        let in_range = |v: f64, len: u32| (0. ..len as f64).contains(&v);
+        if !(in_range(x, self.width) && in_range(y, self.height)) {
+            return None;
+        }
+        Some(self.pixels[y as usize * self.width as usize + x as usize])
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()?
+            .write_image_data(bytemuck::cast_slice(&self.pixels))
+    }
+}
+
+/// Colour used to draw the generator circle with index `i`, and its grips.
+pub fn generator_colour(i: usize) -> [u8; 3] {
+    match colorous::SET1.get(i) {
+        Some(col) => [col.r, col.g, col.b],
+        None => [255, 215, 0],
+    }
+}
+
+/// Converts a fill colour to the displayed colour, the same way the blit
+/// shader does.
+fn display_colour(col: [f32; 4]) -> [u8; 4] {
+    let [r, g, b, a] = col;
+    let shade = |c: f32| ((c / 2. + 0.25) * 255.).round() as u8;
+    [shade(r), shade(g), shade(b), (a * 255.).round() as u8]
+}
+
+/// How a picture is laid over the solved state of a puzzle.
+#[derive(Debug, Clone, Copy)]
+enum TextureMapping {
+    /// Equirectangular, through the stereographic projection, with the origin
+    /// at the centre of the image and infinity on the left and right edges.
+    /// Points are scaled by `scale` first to project from the unit sphere.
+    Sphere { scale: f64 },
+    /// Covering a square, keeping its aspect ratio by cropping the longer
+    /// side
+    Square { centre: Pos, half_width: f64 },
+}
+impl TextureMapping {
+    /// The sphere for spherical puzzles, the Poincaré disk for hyperbolic
+    /// ones, and the bounding box of the circles for euclidean ones.
+    fn new(puzzle: &PuzzleDefinition) -> Self {
+        let curvature = puzzle.curvature;
+        if curvature.is_spherical() {
+            TextureMapping::Sphere {
+                scale: curvature.model_coefficient().sqrt(),
+            }
+        } else if curvature.is_hyperbolic() {
+            TextureMapping::Square {
+                centre: Pos::ORIGIN,
+                half_width: curvature.model_bound(),
+            }
+        } else {
+            let (min, max) = puzzle.bounds();
+            let half_width = (max.x - min.x).max(max.y - min.y) / 2.;
+            match half_width > 0. {
+                true => TextureMapping::Square {
+                    centre: 0.5 * (min + max),
+                    half_width,
+                },
+                false => TextureMapping::Square {
+                    centre: Pos::ORIGIN,
+                    half_width: 1.,
+                },
+            }
+        }
+    }
+
+    /// Colour of `texture` at `point`, or `None` if the point is off the
+    /// image or the image is transparent there.
+    fn colour(self, texture: &Image, point: Pos) -> Option<[u8; 4]> {
+        let (w, h) = (texture.width as f64, texture.height as f64);
+        let (x, y) = match self {
+            TextureMapping::Sphere { scale } => {
+                let point = scale * point;
+                let r2 = point.dist_sq(&Pos::ORIGIN);
+                let (lon, lat) = if r2.is_finite() {
+                    let [x, y, z] = [2. * point.x, 2. * point.y, 1. - r2].map(|c| c / (1. + r2));
+                    (x.atan2(z), y.clamp(-1., 1.).asin())
+                } else {
+                    (PI, 0.)
+                };
+                (
+                    ((lon / TAU + 0.5) * w).min(w - 1.),
+                    ((0.5 - lat / PI) * h).min(h - 1.),
+                )
+            }
+            TextureMapping::Square { centre, half_width } => {
+                let s = w.min(h) / 2. / half_width;
+                (
+                    w / 2. + (point.x - centre.x) * s,
+                    h / 2. - (point.y - centre.y) * s,
+                )
+            }
+        };
+        texture.pixel_at(x, y).filter(|col| col[3] > 0)
+    }
+}
+
+/// Autofills the whole view, seeding from every pixel not yet covered.
+pub fn render(explorer: &Explorer, view: &View) -> Image {
+    let mut image = Image::new(view.width, view.height);
+    let mut filled = bitvec![0; image.pixels.len()];
+    let curvature = explorer.puzzle.curvature;
+    let width = view.width as usize;
+    let unit = view.unit();
+    let mut circles = vec![];
+
+    for index in 0..image.pixels.len() {
+        if filled[index] {
+            continue;
+        }
+        let (x, y) = (index % width, index / width);
+        let seed = view.pixel_to_geom(x as f64 + 0.5, y as f64 + 0.5);
+        filled.set(index, true);
+        if !seed.is_in_space(curvature) {
+            continue;
+        }
+
+        circles.clear();
+        explorer.expand_seed(seed, &view.camera, &mut circles);
+        if let Some(circle) = circles.first() {
+            image.pixels[index] = display_colour(circle.col);
+        }
+        for circle in &circles {
+            fill_circle(&mut image, &mut filled, view, unit, circle);
+        }
+    }
+    image
+}
+
+fn fill_circle(
+    image: &mut Image,
+    filled: &mut BitSlice,
+    view: &View,
+    unit: f64,
+    circle: &GraphicsCircle,
+) {
+    let (cx, cy) = view.screen_to_pixel(Pos::new(circle.centre[0] as f64, circle.centre[1] as f64));
+    let r = circle.radius as f64 * unit;
+    if !(cx.is_finite() && cy.is_finite() && r.is_finite()) {
+        return;
+    }
+    let col = display_colour(circle.col);
+    let width = image.width as usize;
+    let height = image.height as usize;
+    let (top, bottom) = match circle.inverted {
+        true => (0, height),
+        false => (
+            (cy - r).floor().max(0.) as usize,
+            ((cy + r).ceil().max(0.) as usize).min(height),
+        ),
+    };
+    let mut fill = |row: usize, span: std::ops::Range<usize>| {
+        let span = row * width + span.start..row * width + span.end;
+        image.pixels[span.clone()].fill(col);
+        filled[span].fill(true);
+    };
+    for row in top..bottom {
+        let dy = row as f64 + 0.5 - cy;
+        let half_width = (r * r - dy * dy).sqrt();
+        let start = (cx - half_width - 0.5).ceil().clamp(0., width as f64) as usize;
+        let end = (cx + half_width + 0.5).floor().clamp(0., width as f64) as usize;
+        let inside = match half_width.is_nan() {
+            true => 0..0,
+            false => start..end.max(start),
+        };
+        match circle.inverted {
+            true => {
+                fill(row, 0..inside.start);
+                fill(row, inside.end..width);
+            }
+            false => fill(row, inside),
+        }
+    }
+}
+
+/// Renders the puzzle after `moves`, by pulling each pixel back through the
+/// moves in reverse and colouring it as the solved state. Unlike stamping
+/// orbits, this is exact for every pixel, including in jumbling puzzles.
+///
+/// The solved state is `texture` where it covers the space, laid over the
+/// sphere, the Poincaré disk or the circles as suits the curvature, and the
+/// usual sticker colours elsewhere.
+pub fn render_state(
+    explorer: &Explorer,
+    moves: &[Move],
+    view: &View,
+    texture: Option<&Image>,
+) -> Image {
+    let mut image = Image::new(view.width, view.height);
+    let puzzle = &explorer.puzzle;
+    let mapping = TextureMapping::new(puzzle);
+    let width = view.width as usize;
+    let turns = puzzle.turns();
+    let undo = moves
+        .iter()
+        .rev()
+        .filter_map(|mv| mv.reversed().turn(&turns))
+        .collect::<Vec<_>>();
+    for (index, pixel) in image.pixels.iter_mut().enumerate() {
+        let (x, y) = (index % width, index / width);
+        let mut point = view.pixel_to_geom(x as f64 + 0.5, y as f64 + 0.5);
+        if !point.is_in_space(puzzle.curvature) {
+            continue;
+        }
+        for turn in &undo {
+            point = turn.apply(point).unwrap_or(point);
+        }
+        *pixel = texture
+            .and_then(|texture| mapping.colour(texture, point))
+            .unwrap_or_else(|| display_colour(puzzle.solved_colour(point)));
+    }
+    image
+}
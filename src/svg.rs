@@ -0,0 +1,124 @@
+//! Vector export of the circles drawn over the canvas.
+
+use std::fmt::Write;
+
+use crate::geom::{Circle, Pos, Shape};
+use crate::puzzle::{Explorer, Grip};
+use crate::render::{generator_colour, View};
+
+const LIGHT_GRAY: [u8; 3] = [160, 160, 160];
+
+/// Draws the generator circles, the grips of a piece and optionally their
+/// cut circles, styled like the overlay in the app.
+pub fn export(
+    explorer: &Explorer,
+    view: &View,
+    grips: &[Grip],
+    grip_rad: f64,
+    grip_cuts: bool,
+) -> String {
+    let curvature = explorer.puzzle.curvature;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        view.width, view.height
+    );
+    if curvature.is_hyperbolic() {
+        push_circle(
+            &mut svg,
+            view,
+            Pos::ORIGIN,
+            curvature.model_bound(),
+            None,
+            Some((1., LIGHT_GRAY)),
+        );
+    }
+    for (i, circle) in explorer.puzzle.circles.iter().enumerate() {
+        for boundary in circle.boundaries() {
+            let shape = boundary.shape(&view.camera);
+            push_outline(&mut svg, view, shape, (4., generator_colour(i)));
+        }
+    }
+    for grip @ &Grip { pos, id, .. } in grips {
+        if let Some((cen, rad)) = Circle::new(pos, grip_rad, curvature)
+            .shape(&view.camera)
+            .disc()
+        {
+            push_circle(
+                &mut svg,
+                view,
+                cen,
+                rad,
+                Some(generator_colour(id)),
+                Some((2., LIGHT_GRAY)),
+            );
+        }
+        // Grips found before the circles last changed may name a circle which
+        // is gone
+        let cut = grip_cuts.then(|| grip.cut(&explorer.puzzle)).flatten();
+        if let Some(cut) = cut {
+            for boundary in cut.boundaries() {
+                let shape = boundary.shape(&view.camera);
+                push_outline(&mut svg, view, shape, (2., LIGHT_GRAY));
+            }
+        }
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+/// Appends a `<circle>` given its centre and radius in screen space.
+fn push_circle(
+    svg: &mut String,
+    view: &View,
+    cen: Pos,
+    rad: f64,
+    fill: Option<[u8; 3]>,
+    stroke: Option<(f64, [u8; 3])>,
+) {
+    let (cx, cy) = view.screen_to_pixel(cen);
+    let r = rad * view.unit();
+    if !(cx.is_finite() && cy.is_finite() && r.is_finite()) {
+        return;
+    }
+    let fill = match fill {
+        Some(col) => hex(col),
+        None => "none".to_string(),
+    };
+    let stroke = match stroke {
+        Some((width, col)) => format!(" stroke=\"{}\" stroke-width=\"{width}\"", hex(col)),
+        None => String::new(),
+    };
+    writeln!(
+        svg,
+        "  <circle cx=\"{cx:.3}\" cy=\"{cy:.3}\" r=\"{r:.3}\" fill=\"{fill}\"{stroke}/>"
+    )
+    .expect("Writing to a String can't fail");
+}
+
+/// Appends the edge of `shape`, a `<circle>` or a `<line>` reaching past
+/// the edges of the view.
+fn push_outline(svg: &mut String, view: &View, shape: Shape, (width, col): (f64, [u8; 3])) {
+    let (point, normal) = match shape {
+        Shape::Disc { cen, rad } | Shape::Exterior { cen, rad } => {
+            return push_circle(svg, view, cen, rad, None, Some((width, col)));
+        }
+        Shape::HalfPlane { point, normal } => (point, normal),
+    };
+    let reach = (view.width + view.height) as f64 / view.unit();
+    let along = reach * Pos::new(-normal.y, normal.x);
+    let (x1, y1) = view.screen_to_pixel(point - along);
+    let (x2, y2) = view.screen_to_pixel(point + along);
+    if ![x1, y1, x2, y2].iter().all(|c| c.is_finite()) {
+        return;
+    }
+    writeln!(
+        svg,
+        "  <line x1=\"{x1:.3}\" y1=\"{y1:.3}\" x2=\"{x2:.3}\" y2=\"{y2:.3}\" stroke=\"{}\" stroke-width=\"{width}\"/>",
+        hex(col)
+    )
+    .expect("Writing to a String can't fail");
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -0,0 +1,116 @@
+//! Sampling puzzles over a grid of ring distances and circle radii, to find
+//! where they start jumbling.
+
+use std::ops::RangeInclusive;
+
+use crate::geom::RotCircle;
+use crate::puzzle::{Explorer, PuzzleDefinition};
+
+/// A grid of puzzles generated from `template` by moving the circles to a
+/// ring of each distance and giving them all each radius.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    /// Circles whose turns, mirrors, holes, links and inversions are kept,
+    /// along with the curvature, while they are moved and resized
+    pub template: PuzzleDefinition,
+    pub distances: RangeInclusive<f64>,
+    pub radii: RangeInclusive<f64>,
+    /// Number of samples along each axis
+    pub steps: usize,
+    pub depth: u32,
+}
+
+/// What was found for one puzzle in a sweep.
+#[derive(Debug, Clone)]
+pub struct SweepCell {
+    pub distance: f64,
+    pub radius: f64,
+    /// Size of the largest orbit found
+    pub max_orbit: usize,
+    /// Whether any orbit hit the depth limit
+    pub truncated: bool,
+    /// Number of orbits found, only a lower bound if `truncated`
+    pub orbit_count: usize,
+}
+
+impl Sweep {
+    /// The `i`th of `steps` evenly spaced values in `range`.
+    fn sample(range: &RangeInclusive<f64>, steps: usize, i: usize) -> f64 {
+        if steps <= 1 {
+            return *range.start();
+        }
+        range.start() + (range.end() - range.start()) * i as f64 / (steps - 1) as f64
+    }
+
+    pub fn distance(&self, i: usize) -> f64 {
+        Self::sample(&self.distances, self.steps, i)
+    }
+
+    pub fn radius(&self, j: usize) -> f64 {
+        Self::sample(&self.radii, self.steps, j)
+    }
+
+    /// The template with each circle moved onto the ring, its linked
+    /// circles moving with it, and every component given `radius`.
+    pub fn puzzle(&self, distance: f64, radius: f64) -> PuzzleDefinition {
+        let mut puzzle = self.template.with_ring_distance(distance);
+        let resize = |circle: &mut RotCircle| {
+            circle.circle.rad = radius;
+            circle.hole_rad = circle.hole_rad.min(RotCircle::MAX_HOLE * radius);
+        };
+        for circle in &mut puzzle.circles {
+            resize(circle);
+            circle.linked.iter_mut().for_each(resize);
+        }
+        puzzle
+    }
+
+    /// Samples the cell with distance index `i` and radius index `j`.
+    pub fn cell(&self, i: usize, j: usize) -> SweepCell {
+        let (distance, radius) = (self.distance(i), self.radius(j));
+        let explorer = Explorer::new(self.puzzle(distance, radius), self.depth);
+        let census = explorer.census();
+        SweepCell {
+            distance,
+            radius,
+            max_orbit: census.iter().map(|&(len, _)| len).max().unwrap_or(0),
+            truncated: census.iter().any(|&(_, truncated)| truncated),
+            orbit_count: census.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geom::{Curvature, RotCircle};
+    use crate::puzzle::PuzzleDefinition;
+
+    use super::Sweep;
+
+    #[test]
+    fn keeps_links_and_holes() {
+        let mut template = PuzzleDefinition::regular(3, 3., Curvature::EUCLIDEAN);
+        template.circles[0].hole_rad = 0.2;
+        assert!(template.link(1, 2));
+        let sweep = Sweep {
+            template: template.clone(),
+            distances: 1. ..=2.,
+            radii: 0.3..=0.6,
+            steps: 2,
+            depth: 10,
+        };
+        let puzzle = sweep.puzzle(2., 0.2);
+        assert_eq!(puzzle.circles.len(), 2);
+        assert_eq!(puzzle.circles[0].hole_rad, RotCircle::MAX_HOLE * 0.2);
+        let [old, new] = [&template, &puzzle].map(|p| {
+            let primary = &p.circles[1];
+            primary.circle.cen.dist(&primary.linked[0].circle.cen)
+        });
+        assert_eq!(puzzle.circles[1].linked.len(), 1);
+        assert!((old - new).abs() < 1e-12);
+        assert!(puzzle
+            .circles
+            .iter()
+            .all(|c| c.components().all(|c| c.circle.rad == 0.2)));
+    }
+}